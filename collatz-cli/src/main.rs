@@ -0,0 +1,256 @@
+//! Command-line prover and verifier for the Collatz AIRs.
+//!
+//! ```bash
+//! collatz prove --start 27 --backend plonky3 --out proof.bin
+//! collatz prove --start 27 --backend plonky3 --field goldilocks --zk --out proof.bin
+//! collatz verify proof.bin --start 27 --steps 111
+//! collatz inspect proof.bin
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use collatz_core::{
+    Backend, BigUint, CollatzClaim, ProofContainer, SequenceLimits, TraceLayout, PROOF_MAGIC,
+};
+
+#[derive(Parser)]
+#[command(
+    name = "collatz",
+    version,
+    about = "Prove and verify Collatz sequences"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Proves that a starting value reaches 1, and writes the proof to a file
+    Prove {
+        /// The starting value of the sequence
+        #[arg(long)]
+        start: BigUint,
+        /// The proving system: winterfell or plonky3
+        #[arg(long, default_value = "winterfell")]
+        backend: Backend,
+        /// Where to write the proof
+        #[arg(long)]
+        out: PathBuf,
+        /// Write the proof as JSON instead of binary
+        #[arg(long)]
+        json: bool,
+        /// The security preset: testing, conjectured100 (the default), or proven128 with winterfell and
+        /// conjectured128 with plonky3
+        #[arg(long)]
+        preset: Option<String>,
+        /// The base field: f62, f64 or f128 with winterfell, babybear, koalabear, goldilocks or mersenne31
+        /// with plonky3
+        #[arg(long)]
+        field: Option<String>,
+        /// The hash: blake3, sha3, rp64 or rpjive64 with winterfell, keccak256 or poseidon2 with plonky3
+        #[arg(long)]
+        hash: Option<String>,
+        /// Make the proof zero knowledge
+        #[arg(long)]
+        zk: bool,
+    },
+    /// Verifies that a proof shows that `start` reaches 1 in `steps` steps
+    Verify {
+        /// The proof file, binary or JSON
+        proof: PathBuf,
+        #[arg(long)]
+        start: BigUint,
+        #[arg(long)]
+        steps: u32,
+        /// Minimum conjectured security of the proof in bits, instead of the one of the backend's
+        /// default preset
+        #[arg(long)]
+        min_security: Option<u32>,
+    },
+    /// Prints the parameters, claim and sizes of a proof
+    Inspect {
+        /// The proof file, binary or JSON
+        proof: PathBuf,
+    },
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    match cli.command {
+        Command::Prove {
+            start,
+            backend,
+            out,
+            json,
+            preset,
+            field,
+            hash,
+            zk,
+        } => {
+            let limits = SequenceLimits::default();
+            let container = match backend {
+                Backend::Winterfell => {
+                    use winterfell_collatz::config::{
+                        FieldChoice, HashChoice, Primitives, SecurityPreset,
+                    };
+                    let preset = parse_choice(&SecurityPreset::ALL, "preset", preset)?;
+                    let mut primitives = Primitives::default();
+                    if let Some(field) = parse_choice(&FieldChoice::ALL, "field", field)? {
+                        primitives.field = field;
+                    }
+                    if let Some(hash) = parse_choice(&HashChoice::ALL, "hash", hash)? {
+                        primitives.hash = hash;
+                    }
+                    winterfell_collatz::prove(
+                        start,
+                        &limits,
+                        preset.unwrap_or_default().proof_options(primitives.field),
+                        primitives,
+                        zk,
+                    )?
+                }
+                Backend::Plonky3 => {
+                    use plonky3_collatz::config::{
+                        CollatzStarkConfig, FieldChoice, HashChoice, SecurityPreset,
+                    };
+                    let preset = parse_choice(&SecurityPreset::ALL, "preset", preset)?;
+                    let mut config = CollatzStarkConfig::from_preset(preset.unwrap_or_default());
+                    if let Some(field) = parse_choice(&FieldChoice::ALL, "field", field)? {
+                        config = config.with_field(field);
+                    }
+                    if let Some(hash) = parse_choice(&HashChoice::ALL, "hash", hash)? {
+                        config = config.with_hash(hash);
+                    }
+                    plonky3_collatz::prove(start, &limits, &config.with_zk(zk))?
+                }
+            };
+            let bytes = if json {
+                container.to_json()?.into_bytes()
+            } else {
+                container.to_bytes()?
+            };
+            fs::write(&out, &bytes)?;
+            println!(
+                "Proved that {} reaches 1 in {} steps with {}, wrote {} bytes to {}",
+                container.claim.starting_value,
+                container.claim.steps_count,
+                backend,
+                bytes.len(),
+                out.display()
+            );
+        }
+        Command::Verify {
+            proof,
+            start,
+            steps,
+            min_security,
+        } => {
+            let container = read_container(&proof)?;
+            let claim = CollatzClaim::new(start, steps);
+            match min_security {
+                Some(min_security) => collatz_verifier::verify_container_with_min_security(
+                    &claim,
+                    &container,
+                    min_security,
+                )?,
+                None => collatz_verifier::verify_container(&claim, &container)?,
+            }
+            println!(
+                "Verified that {} reaches 1 in {} steps",
+                claim.starting_value, claim.steps_count
+            );
+        }
+        Command::Inspect { proof } => {
+            let size = fs::metadata(&proof)?.len();
+            let container = read_container(&proof)?;
+            let layout = TraceLayout::new(container.num_bits as usize);
+            // The trace is padded to the minimum length of the options the proof was produced with, and
+            // Winterfell's zero-knowledge traces end with random rows, so its proofs record the length.
+            // Plonky3's limbs are as wide as the field allows.
+            let (trace_length, layout) = match container.backend {
+                Backend::Winterfell => (winterfell_collatz::trace_length(&container)?, layout),
+                Backend::Plonky3 => {
+                    let config = plonky3_collatz::config::CollatzStarkConfig::from_parameters(
+                        &container.parameters,
+                    )
+                    .ok_or("unsupported plonky3 parameters")?;
+                    (
+                        container.claim.trace_length(config.min_trace_length()),
+                        layout.for_field(config.field().bits()),
+                    )
+                }
+            };
+            println!("backend:      {}", container.backend);
+            println!("field:        {}", container.parameters.field);
+            println!("hash:         {}", container.parameters.hash);
+            println!("pcs:          {}", container.parameters.pcs);
+            println!("fri:          {}", container.parameters.fri);
+            println!(
+                "claim:        {} reaches 1 in {} steps",
+                container.claim.starting_value, container.claim.steps_count
+            );
+            println!(
+                "trace:        {} rows, {} columns ({} bit columns)",
+                trace_length,
+                layout.width(),
+                layout.num_bits()
+            );
+            println!(
+                "proof size:   {} bytes ({} bytes with the container)",
+                container.proof.len(),
+                size
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The choice among `all` named `name`, by its `Debug` name in any case.
+fn parse_choice<T: Copy + fmt::Debug>(
+    all: &[T],
+    kind: &str,
+    name: Option<String>,
+) -> Result<Option<T>, String> {
+    let Some(name) = name else {
+        return Ok(None);
+    };
+    let found = all
+        .iter()
+        .find(|choice| format!("{choice:?}").eq_ignore_ascii_case(&name));
+    match found {
+        Some(choice) => Ok(Some(*choice)),
+        None => {
+            let names: Vec<String> = all.iter().map(|choice| format!("{choice:?}")).collect();
+            Err(format!(
+                "unknown {kind} `{name}`, expected one of {}",
+                names.join(", ").to_lowercase()
+            ))
+        }
+    }
+}
+
+/// Reads a proof container, in binary if it starts with the magic bytes and in JSON otherwise.
+fn read_container(path: &Path) -> Result<ProofContainer, Box<dyn Error>> {
+    let bytes = fs::read(path)?;
+    let container = if bytes.starts_with(&PROOF_MAGIC) {
+        ProofContainer::from_bytes(&bytes)?
+    } else {
+        ProofContainer::from_json(std::str::from_utf8(&bytes)?)?
+    };
+    Ok(container)
+}
@@ -0,0 +1,17 @@
+use crate::claim::CollatzClaim;
+use crate::container::Backend;
+use crate::error::CollatzError;
+
+/// Common interface of the proving backends, so that applications can switch between them by configuration.
+pub trait CollatzBackend {
+    type Proof;
+
+    /// Which proving system this is.
+    fn backend(&self) -> Backend;
+
+    /// Proves `claim`, failing if it is not true.
+    fn prove(&self, claim: &CollatzClaim) -> Result<Self::Proof, CollatzError>;
+
+    /// Verifies that `proof` proves `claim`.
+    fn verify(&self, claim: &CollatzClaim, proof: &Self::Proof) -> Result<(), CollatzError>;
+}
@@ -0,0 +1,41 @@
+use num_bigint::BigUint;
+
+use crate::witness::CollatzWitness;
+
+/// The public statement proven by both backends: `starting_value` reaches 1 for the first time
+/// after exactly `steps_count` steps.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "json",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
+pub struct CollatzClaim {
+    #[cfg_attr(feature = "json", serde(with = "crate::container::decimal"))]
+    pub starting_value: BigUint,
+    pub steps_count: u32,
+}
+
+impl CollatzClaim {
+    pub fn new(starting_value: impl Into<BigUint>, steps_count: u32) -> Self {
+        Self {
+            starting_value: starting_value.into(),
+            steps_count,
+        }
+    }
+
+    /// Number of rows of the honest trace for this claim, for a backend whose traces have at least
+    /// `min_trace_length` rows (see [`CollatzWitness`] for the padding policy).
+    pub fn trace_length(&self, min_trace_length: usize) -> usize {
+        (self.steps_count as usize + 1)
+            .max(min_trace_length)
+            .next_power_of_two()
+    }
+}
+
+impl From<&CollatzWitness> for CollatzClaim {
+    /// The true claim about the witness' starting value.
+    fn from(witness: &CollatzWitness) -> Self {
+        Self::new(witness.starting_value().clone(), witness.steps())
+    }
+}
@@ -0,0 +1,623 @@
+use std::fmt;
+
+use num_bigint::BigUint;
+
+use crate::claim::CollatzClaim;
+
+/// First bytes of every binary proof container.
+pub const PROOF_MAGIC: [u8; 4] = *b"CLTZ";
+/// Version of the binary proof container, bumped on every incompatible change of its layout.
+pub const PROOF_FORMAT_VERSION: u16 = 2;
+
+/// The proving system a proof was produced with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "json",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Backend {
+    Winterfell,
+    Plonky3,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 2] = [Backend::Winterfell, Backend::Plonky3];
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Backend::Winterfell => "winterfell",
+            Backend::Plonky3 => "plonky3",
+        }
+    }
+
+    const fn id(&self) -> u8 {
+        match self {
+            Backend::Winterfell => 0,
+            Backend::Plonky3 => 1,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|backend| backend.id() == id)
+    }
+}
+
+impl std::str::FromStr for Backend {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|backend| backend.name() == name)
+            .ok_or_else(|| format!("unknown backend `{name}`, expected winterfell or plonky3"))
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Description of the field, hash function and polynomial commitment scheme a proof was produced with.
+///
+/// A verifier only accepts a container whose parameters are exactly the ones it is configured with:
+/// proof bytes are not self-describing, so any difference would at best fail to parse, and at worst
+/// verify under a weaker configuration than intended.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "json",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
+pub struct ProofParameters {
+    /// Base field and, if any, the extension field challenges are drawn from.
+    pub field: String,
+    /// Hash function of the commitments and of the Fiat-Shamir transcript.
+    pub hash: String,
+    /// Polynomial commitment scheme, with the choices which are not in [`ProofParameters::fri`].
+    pub pcs: String,
+    /// Parameters of the FRI low-degree test of the PCS.
+    pub fri: FriParameters,
+}
+
+impl fmt::Display for ProofParameters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "field: {}, hash: {}, pcs: {}, {}",
+            self.field, self.hash, self.pcs, self.fri
+        )
+    }
+}
+
+/// Parameters of the FRI low-degree test, which determine the security of a proof and the cost of verifying
+/// it. They are recorded as numbers, so that verifiers can rebuild their configuration from a container
+/// without parsing its descriptions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "json",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
+pub struct FriParameters {
+    /// Log of the blowup factor of the low-degree extension.
+    pub log_blowup: u32,
+    pub num_queries: u32,
+    /// Bits of proof of work before the query positions are drawn.
+    pub proof_of_work_bits: u32,
+    /// Log of the number of coefficients of the polynomial FRI folds down to.
+    pub log_final_poly_len: u32,
+}
+
+impl fmt::Display for FriParameters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "log blowup: {}, queries: {}, pow bits: {}, log final poly len: {}",
+            self.log_blowup, self.num_queries, self.proof_of_work_bits, self.log_final_poly_len
+        )
+    }
+}
+
+/// A proof together with everything needed to check it: the backend and parameters it was produced
+/// with, the claim it proves, and the width of its trace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofContainer {
+    pub backend: Backend,
+    pub parameters: ProofParameters,
+    pub claim: CollatzClaim,
+    /// Number of bit columns N of the trace (see [`crate::TraceLayout`]).
+    pub num_bits: u32,
+    /// The backend-specific serialization of the proof.
+    pub proof: Vec<u8>,
+}
+
+/// Reasons why bytes could not be read as a proof container for a given backend and parameters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProofFormatError {
+    /// The bytes do not start with [`PROOF_MAGIC`].
+    BadMagic,
+    /// The container was written with another version of the format.
+    UnsupportedVersion(u16),
+    UnknownBackend(u8),
+    /// The bytes end in the middle of the container.
+    Truncated,
+    /// There are bytes left after the container.
+    TrailingBytes(usize),
+    /// A field of the container is not in its canonical encoding.
+    Malformed(&'static str),
+    /// A field of the container is too long for its length to be encoded as a `u32`.
+    TooLong(&'static str),
+    BackendMismatch {
+        expected: Backend,
+        found: Backend,
+    },
+    ParameterMismatch {
+        expected: Box<ProofParameters>,
+        found: Box<ProofParameters>,
+    },
+    /// The JSON representation could not be parsed, or written.
+    Json(String),
+}
+
+impl fmt::Display for ProofFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofFormatError::BadMagic => write!(f, "not a Collatz proof container"),
+            ProofFormatError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported proof format version {version}, expected {PROOF_FORMAT_VERSION}"
+            ),
+            ProofFormatError::UnknownBackend(id) => write!(f, "unknown backend id {id}"),
+            ProofFormatError::Truncated => write!(f, "the proof container is truncated"),
+            ProofFormatError::TrailingBytes(count) => {
+                write!(f, "{count} unexpected bytes after the proof container")
+            }
+            ProofFormatError::Malformed(field) => write!(f, "malformed {field}"),
+            ProofFormatError::TooLong(field) => write!(f, "{field} is too long to be encoded"),
+            ProofFormatError::BackendMismatch { expected, found } => write!(
+                f,
+                "the proof was produced by the {found} backend, expected {expected}"
+            ),
+            ProofFormatError::ParameterMismatch { expected, found } => write!(
+                f,
+                "the proof parameters ({found}) do not match the expected ones ({expected})"
+            ),
+            ProofFormatError::Json(error) => write!(f, "invalid JSON proof container: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for ProofFormatError {}
+
+impl ProofContainer {
+    /// Binary encoding of the container. All integers are little-endian, and variable-length fields
+    /// are prefixed with their length as a `u32`:
+    ///
+    /// `magic | version: u16 | backend: u8 | field | hash | pcs | log_blowup: u32 | num_queries: u32 |
+    /// proof_of_work_bits: u32 | log_final_poly_len: u32 | num_bits: u32 | steps_count: u32 |
+    /// starting_value | proof`
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ProofFormatError> {
+        let mut bytes = Vec::with_capacity(self.proof.len() + 64);
+        bytes.extend_from_slice(&PROOF_MAGIC);
+        bytes.extend_from_slice(&PROOF_FORMAT_VERSION.to_le_bytes());
+        bytes.push(self.backend.id());
+        write_bytes(&mut bytes, "field", self.parameters.field.as_bytes())?;
+        write_bytes(&mut bytes, "hash", self.parameters.hash.as_bytes())?;
+        write_bytes(&mut bytes, "pcs", self.parameters.pcs.as_bytes())?;
+        let fri = &self.parameters.fri;
+        for value in [
+            fri.log_blowup,
+            fri.num_queries,
+            fri.proof_of_work_bits,
+            fri.log_final_poly_len,
+        ] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.num_bits.to_le_bytes());
+        bytes.extend_from_slice(&self.claim.steps_count.to_le_bytes());
+        write_bytes(
+            &mut bytes,
+            "starting value",
+            &self.claim.starting_value.to_bytes_le(),
+        )?;
+        write_bytes(&mut bytes, "proof", &self.proof)?;
+        Ok(bytes)
+    }
+
+    /// Parses a binary container, without checking its backend or parameters.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProofFormatError> {
+        let mut reader = Reader { bytes };
+        if reader.take(PROOF_MAGIC.len())? != PROOF_MAGIC {
+            return Err(ProofFormatError::BadMagic);
+        }
+        let version = reader.read_u16()?;
+        if version != PROOF_FORMAT_VERSION {
+            return Err(ProofFormatError::UnsupportedVersion(version));
+        }
+        let backend_id = reader.read_u8()?;
+        let backend =
+            Backend::from_id(backend_id).ok_or(ProofFormatError::UnknownBackend(backend_id))?;
+        let parameters = ProofParameters {
+            field: reader.read_string("field")?,
+            hash: reader.read_string("hash")?,
+            pcs: reader.read_string("pcs")?,
+            fri: FriParameters {
+                log_blowup: reader.read_u32()?,
+                num_queries: reader.read_u32()?,
+                proof_of_work_bits: reader.read_u32()?,
+                log_final_poly_len: reader.read_u32()?,
+            },
+        };
+        let num_bits = reader.read_u32()?;
+        let steps_count = reader.read_u32()?;
+        let starting_value = reader.read_bytes()?;
+        // Reject redundant leading zeros, so that every container has a single encoding
+        if starting_value.last() == Some(&0) {
+            return Err(ProofFormatError::Malformed("starting value"));
+        }
+        let proof = reader.read_bytes()?.to_vec();
+        if !reader.bytes.is_empty() {
+            return Err(ProofFormatError::TrailingBytes(reader.bytes.len()));
+        }
+
+        Ok(Self {
+            backend,
+            parameters,
+            claim: CollatzClaim {
+                starting_value: BigUint::from_bytes_le(starting_value),
+                steps_count,
+            },
+            num_bits,
+            proof,
+        })
+    }
+
+    /// Parses a binary container, rejecting it unless it was produced by `backend` with exactly `parameters`.
+    pub fn from_bytes_strict(
+        bytes: &[u8],
+        backend: Backend,
+        parameters: &ProofParameters,
+    ) -> Result<Self, ProofFormatError> {
+        let container = Self::from_bytes(bytes)?;
+        container.check(backend, parameters)?;
+        Ok(container)
+    }
+
+    /// Checks that the container was produced by `backend` with exactly `parameters`.
+    pub fn check(
+        &self,
+        backend: Backend,
+        parameters: &ProofParameters,
+    ) -> Result<(), ProofFormatError> {
+        if self.backend != backend {
+            return Err(ProofFormatError::BackendMismatch {
+                expected: backend,
+                found: self.backend,
+            });
+        }
+        if self.parameters != *parameters {
+            return Err(ProofFormatError::ParameterMismatch {
+                expected: Box::new(parameters.clone()),
+                found: Box::new(self.parameters.clone()),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "json")]
+impl ProofContainer {
+    /// Human-readable encoding of the container, with the starting value in decimal and the proof in hex.
+    pub fn to_json(&self) -> Result<String, ProofFormatError> {
+        let container = JsonContainer {
+            version: PROOF_FORMAT_VERSION,
+            backend: self.backend,
+            parameters: self.parameters.clone(),
+            claim: self.claim.clone(),
+            num_bits: self.num_bits,
+            proof: self.proof.clone(),
+        };
+        serde_json::to_string_pretty(&container)
+            .map_err(|error| ProofFormatError::Json(error.to_string()))
+    }
+
+    /// Parses a JSON container, without checking its backend or parameters.
+    pub fn from_json(json: &str) -> Result<Self, ProofFormatError> {
+        let container: JsonContainer = serde_json::from_str(json)
+            .map_err(|error| ProofFormatError::Json(error.to_string()))?;
+        if container.version != PROOF_FORMAT_VERSION {
+            return Err(ProofFormatError::UnsupportedVersion(container.version));
+        }
+        Ok(Self {
+            backend: container.backend,
+            parameters: container.parameters,
+            claim: container.claim,
+            num_bits: container.num_bits,
+            proof: container.proof,
+        })
+    }
+}
+
+#[cfg(feature = "json")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonContainer {
+    version: u16,
+    backend: Backend,
+    parameters: ProofParameters,
+    claim: CollatzClaim,
+    num_bits: u32,
+    #[serde(with = "hex")]
+    proof: Vec<u8>,
+}
+
+/// Serializes a `BigUint` as a decimal string.
+#[cfg(feature = "json")]
+pub(crate) mod decimal {
+    use num_bigint::BigUint;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &BigUint, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BigUint, D::Error> {
+        let digits = String::deserialize(deserializer)?;
+        // `BigUint::from_str` accepts a leading `+`, underscores and leading zeros, which a canonical encoding
+        // must not have
+        if digits.is_empty()
+            || !digits.bytes().all(|b| b.is_ascii_digit())
+            || (digits.len() > 1 && digits.starts_with('0'))
+        {
+            return Err(D::Error::custom("expected a decimal integer"));
+        }
+        digits.parse().map_err(D::Error::custom)
+    }
+}
+
+/// Serializes bytes as a lowercase hex string.
+#[cfg(feature = "json")]
+mod hex {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if hex.len() % 2 != 0 {
+            return Err(D::Error::custom("odd number of hex digits"));
+        }
+        // Only lowercase digits, so that every proof has a single encoding
+        let digit = |c: u8| match c {
+            b'0'..=b'9' => Ok(c - b'0'),
+            b'a'..=b'f' => Ok(c - b'a' + 10),
+            _ => Err(D::Error::custom("invalid hex digit")),
+        };
+        hex.as_bytes()
+            .chunks(2)
+            .map(|pair| Ok(digit(pair[0])? << 4 | digit(pair[1])?))
+            .collect()
+    }
+}
+
+fn write_bytes(
+    out: &mut Vec<u8>,
+    field: &'static str,
+    bytes: &[u8],
+) -> Result<(), ProofFormatError> {
+    let len = u32::try_from(bytes.len()).map_err(|_| ProofFormatError::TooLong(field))?;
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(bytes);
+    Ok(())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ProofFormatError> {
+        if self.bytes.len() < len {
+            return Err(ProofFormatError::Truncated);
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, ProofFormatError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16, ProofFormatError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, ProofFormatError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn read_bytes(&mut self) -> Result<&'a [u8], ProofFormatError> {
+        let len = self.read_u32()? as usize;
+        self.take(len)
+    }
+
+    fn read_string(&mut self, field: &'static str) -> Result<String, ProofFormatError> {
+        let bytes = self.read_bytes()?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| ProofFormatError::Malformed(field))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn container() -> ProofContainer {
+        ProofContainer {
+            backend: Backend::Plonky3,
+            parameters: ProofParameters {
+                field: "BabyBear".to_string(),
+                hash: "Keccak256".to_string(),
+                pcs: "TwoAdicFri".to_string(),
+                fri: FriParameters {
+                    log_blowup: 1,
+                    num_queries: 100,
+                    proof_of_work_bits: 16,
+                    log_final_poly_len: 0,
+                },
+            },
+            claim: CollatzClaim::new(27u32, 111),
+            num_bits: 14,
+            proof: vec![0x00, 0x01, 0xab, 0xff],
+        }
+    }
+
+    /// The encoding of `container()` up to, and excluding, its starting value.
+    fn header(container: &ProofContainer) -> Vec<u8> {
+        let bytes = container.to_bytes().unwrap();
+        let tail =
+            4 + container.claim.starting_value.to_bytes_le().len() + 4 + container.proof.len();
+        bytes[..bytes.len() - tail].to_vec()
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let container = container();
+        let bytes = container.to_bytes().unwrap();
+        assert_eq!(ProofContainer::from_bytes(&bytes), Ok(container.clone()));
+        assert_eq!(
+            ProofContainer::from_bytes_strict(&bytes, Backend::Plonky3, &container.parameters),
+            Ok(container.clone())
+        );
+        assert!(matches!(
+            ProofContainer::from_bytes_strict(&bytes, Backend::Winterfell, &container.parameters),
+            Err(ProofFormatError::BackendMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_truncated_containers() {
+        let bytes = container().to_bytes().unwrap();
+        for len in 0..bytes.len() {
+            assert_eq!(
+                ProofContainer::from_bytes(&bytes[..len]),
+                Err(ProofFormatError::Truncated),
+                "{len} bytes"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_trailing_bytes() {
+        let mut bytes = container().to_bytes().unwrap();
+        bytes.extend_from_slice(&[0, 0]);
+        assert_eq!(
+            ProofContainer::from_bytes(&bytes),
+            Err(ProofFormatError::TrailingBytes(2))
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_magic_and_version() {
+        let mut bytes = container().to_bytes().unwrap();
+        bytes[0] ^= 1;
+        assert_eq!(
+            ProofContainer::from_bytes(&bytes),
+            Err(ProofFormatError::BadMagic)
+        );
+
+        let mut bytes = container().to_bytes().unwrap();
+        bytes[PROOF_MAGIC.len()..PROOF_MAGIC.len() + 2]
+            .copy_from_slice(&(PROOF_FORMAT_VERSION + 1).to_le_bytes());
+        assert_eq!(
+            ProofContainer::from_bytes(&bytes),
+            Err(ProofFormatError::UnsupportedVersion(
+                PROOF_FORMAT_VERSION + 1
+            ))
+        );
+
+        let mut bytes = container().to_bytes().unwrap();
+        bytes[PROOF_MAGIC.len() + 2] = 7;
+        assert_eq!(
+            ProofContainer::from_bytes(&bytes),
+            Err(ProofFormatError::UnknownBackend(7))
+        );
+    }
+
+    #[test]
+    fn rejects_non_canonical_starting_values() {
+        let container = container();
+        // 27 with a redundant leading zero byte
+        let mut bytes = header(&container);
+        write_bytes(&mut bytes, "starting value", &[27, 0]).unwrap();
+        write_bytes(&mut bytes, "proof", &container.proof).unwrap();
+        assert_eq!(
+            ProofContainer::from_bytes(&bytes),
+            Err(ProofFormatError::Malformed("starting value"))
+        );
+
+        let mut bytes = header(&container);
+        write_bytes(&mut bytes, "starting value", &[27]).unwrap();
+        write_bytes(&mut bytes, "proof", &container.proof).unwrap();
+        assert_eq!(ProofContainer::from_bytes(&bytes), Ok(container));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_round_trip() {
+        let container = container();
+        let json = container.to_json().unwrap();
+        assert!(json.contains("\"0001abff\""));
+        assert_eq!(ProofContainer::from_json(&json), Ok(container));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn rejects_malformed_json() {
+        let json = container().to_json().unwrap();
+        let replaced = |from: &str, to: &str| {
+            assert!(json.contains(from), "{from}");
+            ProofContainer::from_json(&json.replace(from, to))
+        };
+
+        for proof in [
+            "0001ABFF", "+f01abff", "0001abf", "0001abfg", "aéa", "0x01abff",
+        ] {
+            assert!(
+                matches!(
+                    replaced("\"0001abff\"", &format!("\"{proof}\"")),
+                    Err(ProofFormatError::Json(_))
+                ),
+                "{proof}"
+            );
+        }
+        for starting_value in ["\"027\"", "\"+27\"", "\"2_7\"", "\"\"", "27"] {
+            assert!(
+                matches!(
+                    replaced("\"27\"", starting_value),
+                    Err(ProofFormatError::Json(_))
+                ),
+                "{starting_value}"
+            );
+        }
+        assert!(matches!(
+            ProofContainer::from_json(&json[..json.len() - 1]),
+            Err(ProofFormatError::Json(_))
+        ));
+        assert!(matches!(
+            replaced("\"num_bits\"", "\"extra\": 0, \"num_bits\""),
+            Err(ProofFormatError::Json(_))
+        ));
+        let version = format!("\"version\": {PROOF_FORMAT_VERSION}");
+        assert_eq!(
+            replaced(&version, "\"version\": 1"),
+            Err(ProofFormatError::UnsupportedVersion(1))
+        );
+    }
+}
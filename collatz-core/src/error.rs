@@ -0,0 +1,70 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::claim::CollatzClaim;
+use crate::sequence::SequenceError;
+
+/// Errors of the provers, the AIRs and the [`crate::CollatzBackend`]s.
+///
+/// Bad inputs and hostile proofs are reported with this error rather than panicking, so that the provers
+/// and verifiers can be embedded in long-running services.
+#[derive(Debug)]
+pub enum CollatzError {
+    /// No witness can be computed for the starting value.
+    InvalidInput(SequenceError),
+    /// The claimed starting value reaches 1 in `steps` steps, not in the claimed number of steps.
+    FalseClaim { claim: CollatzClaim, steps: u32 },
+    /// A value of `bits` bits does not fit in the `num_bits` bit columns of the trace.
+    BitWidthMismatch { bits: usize, num_bits: usize },
+    /// The field modulus has `field_bits` bits, fewer than the `required_bits` of the trace layout.
+    FieldTooSmall { field_bits: u32, required_bits: u32 },
+    /// The trace cannot be built, or does not have the shape the AIR expects.
+    TraceConstruction(String),
+    /// The backend's prover failed.
+    Prover(Box<dyn Error + Send + Sync>),
+    /// The proof was rejected.
+    Verification(Box<dyn Error + Send + Sync>),
+}
+
+impl fmt::Display for CollatzError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CollatzError::InvalidInput(error) => write!(f, "{error}"),
+            CollatzError::FalseClaim { claim, steps } => write!(
+                f,
+                "{} reaches 1 in {steps} steps, not {}",
+                claim.starting_value, claim.steps_count
+            ),
+            CollatzError::BitWidthMismatch { bits, num_bits } => write!(
+                f,
+                "a {bits}-bit value does not fit in the trace's {num_bits} bit columns"
+            ),
+            CollatzError::FieldTooSmall {
+                field_bits,
+                required_bits,
+            } => write!(
+                f,
+                "the {field_bits}-bit field is too small for the limb-wise Collatz rule, which needs {required_bits} bits"
+            ),
+            CollatzError::TraceConstruction(reason) => write!(f, "invalid trace: {reason}"),
+            CollatzError::Prover(error) => write!(f, "proving failed: {error}"),
+            CollatzError::Verification(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl Error for CollatzError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CollatzError::InvalidInput(error) => Some(error),
+            CollatzError::Prover(error) | CollatzError::Verification(error) => Some(&**error),
+            _ => None,
+        }
+    }
+}
+
+impl From<SequenceError> for CollatzError {
+    fn from(error: SequenceError) -> Self {
+        CollatzError::InvalidInput(error)
+    }
+}
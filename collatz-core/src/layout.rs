@@ -0,0 +1,325 @@
+use std::fmt;
+use std::ops::Range;
+
+use num_bigint::BigUint;
+
+use crate::error::CollatzError;
+use crate::witness::{CollatzRow, CollatzWitness};
+
+/// Number of bits in each limb of the current value, unless the layout is widened for a larger field (see
+/// [`TraceLayout::for_field`]).
+pub const LIMB_BITS: usize = 16;
+/// Number of bits in each limb of the current value over fields large enough for them, e.g. Goldilocks.
+pub const WIDE_LIMB_BITS: usize = 32;
+/// Number of bit columns used to encode each carry between limbs.
+pub const CARRY_BITS: usize = 3;
+/// Carries lie in `[-1, 6]`; they are stored shifted by this offset so that they fit in `CARRY_BITS` bits.
+pub const CARRY_OFFSET: u64 = 1;
+
+/// Column layout of the Collatz trace, shared by all backends.
+/// The trace consists of N columns, each representing a bit in the binary representation (LSB first)
+/// of the current number in the sequence, plus additional columns:
+/// - Column N: step counter
+/// - Column N+1: transition flag (1 = transition, 0 = repeat)
+/// - Column N+2: done flag (1 iff the current value is 1)
+/// - Column N+3: inverse of [`TraceLayout::done_sum`] when the done flag is 0, and 0 otherwise
+/// - Columns N+4..: for each limb but the first, `CARRY_BITS` bits of the (offset) carry into that limb
+///
+/// Limbs have [`LIMB_BITS`] bits, or [`WIDE_LIMB_BITS`] over fields large enough for them, which need
+/// fewer limbs, and therefore fewer carry columns, for the same values.
+///
+/// Once the done flag is set, no transition may follow, so the step counter of the last row is the
+/// number of steps to *first* reach 1, rather than that number plus any trips around the `1 -> 4 -> 2 -> 1` cycle.
+///
+/// The Collatz rule is not checked on the whole value at once, as its weighted sum would wrap around
+/// the field for large N. Instead, the bits are grouped into limbs of `limb_bits()` bits and the rule
+/// `2 * next = is_odd * (6 * current + 2) + (1 - is_odd) * current` is checked limb by limb:
+///
+/// `2 * next_k + 2^limb_bits * carry_{k+1} = is_odd * (6 * current_k + 2 * [k == 0]) + (1 - is_odd) * current_k + carry_k`
+///
+/// with `carry_0 = carry_L = 0`. Every such equation stays far below the field modulus (see
+/// [`TraceLayout::required_field_bits`]), so it holds over the integers, and summing them up
+/// recovers the rule on the full values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceLayout {
+    num_bits: usize,
+    limb_bits: usize,
+}
+
+impl TraceLayout {
+    /// The layout with `num_bits` bit columns, grouped into limbs of [`LIMB_BITS`] bits.
+    pub const fn new(num_bits: usize) -> Self {
+        Self {
+            num_bits,
+            limb_bits: LIMB_BITS,
+        }
+    }
+
+    /// The same bit columns, grouped into limbs of [`WIDE_LIMB_BITS`] bits if a field whose modulus has
+    /// `field_bits` bits is large enough for them, and of [`LIMB_BITS`] bits otherwise.
+    pub const fn for_field(self, field_bits: u32) -> Self {
+        let wide = Self {
+            limb_bits: WIDE_LIMB_BITS,
+            ..self
+        };
+        if wide.required_field_bits() <= field_bits {
+            wide
+        } else {
+            Self {
+                limb_bits: LIMB_BITS,
+                ..self
+            }
+        }
+    }
+
+    /// The narrowest layout that can hold every value of `witness`.
+    pub fn for_witness(witness: &CollatzWitness) -> Self {
+        Self::new(witness.max_bits())
+    }
+
+    /// Recovers the layout with limbs of [`LIMB_BITS`] bits from the total number of trace columns, e.g. the
+    /// width reported by a proof.
+    pub fn from_width(width: usize) -> Option<Self> {
+        // The width strictly increases with N, so there is at most one candidate
+        (1..=width)
+            .map(Self::new)
+            .find(|layout| layout.width() == width)
+    }
+
+    /// Number of bit columns, N.
+    pub const fn num_bits(&self) -> usize {
+        self.num_bits
+    }
+
+    /// Number of bits in each limb but the last, which may be shorter.
+    pub const fn limb_bits(&self) -> usize {
+        self.limb_bits
+    }
+
+    /// Number of limbs the bit columns are grouped into.
+    pub const fn num_limbs(&self) -> usize {
+        self.num_bits.div_ceil(self.limb_bits)
+    }
+
+    /// Total number of trace columns.
+    pub const fn width(&self) -> usize {
+        self.num_bits + 4 + self.num_limbs().saturating_sub(1) * CARRY_BITS
+    }
+
+    /// Column holding bit `i` of the current value.
+    pub const fn bit(&self, i: usize) -> usize {
+        i
+    }
+
+    /// Indices of the bits making up limb `k`, least significant first.
+    pub fn limb(&self, k: usize) -> Range<usize> {
+        k * self.limb_bits..self.num_bits.min((k + 1) * self.limb_bits)
+    }
+
+    pub const fn step_counter(&self) -> usize {
+        self.num_bits
+    }
+
+    pub const fn transition_flag(&self) -> usize {
+        self.num_bits + 1
+    }
+
+    pub const fn done_flag(&self) -> usize {
+        self.num_bits + 2
+    }
+
+    pub const fn done_inverse(&self) -> usize {
+        self.num_bits + 3
+    }
+
+    /// Column holding bit `j` of the carry into limb `k`, for `1 <= k < num_limbs()`.
+    pub const fn carry_bit(&self, k: usize, j: usize) -> usize {
+        self.num_bits + 4 + (k - 1) * CARRY_BITS + j
+    }
+
+    /// All carry bit columns.
+    pub fn carry_columns(&self) -> Range<usize> {
+        self.num_bits + 4..self.width()
+    }
+
+    /// Minimum number of bits of the field modulus for the constraints to be sound with this layout.
+    ///
+    /// The largest expression the limb-wise Collatz rule can take on binary cells is below
+    /// `2^(limb_bits() + 4)`, and the done sum lies in `[-1, 2 * num_limbs() * 2^limb_bits())`, so any field
+    /// with a modulus above both bounds evaluates them without wrap-around.
+    pub const fn required_field_bits(&self) -> u32 {
+        let rule_bits = self.limb_bits as u32 + 5;
+        let done_bits =
+            self.limb_bits as u32 + 2 + self.num_limbs().next_power_of_two().trailing_zeros();
+        if rule_bits > done_bits {
+            rule_bits
+        } else {
+            done_bits
+        }
+    }
+
+    /// Whether `value` can be represented with the N bit columns.
+    pub fn fits(&self, value: &BigUint) -> bool {
+        value.bits() as usize <= self.num_bits
+    }
+
+    /// Checks that `value` can be represented with the N bit columns.
+    pub fn check_fits(&self, value: &BigUint) -> Result<(), CollatzError> {
+        if self.fits(value) {
+            Ok(())
+        } else {
+            Err(CollatzError::BitWidthMismatch {
+                bits: value.bits() as usize,
+                num_bits: self.num_bits,
+            })
+        }
+    }
+
+    /// Checks that a field whose modulus has `field_bits` bits is large enough for this layout.
+    pub fn check_field_bits(&self, field_bits: u32) -> Result<(), CollatzError> {
+        let required_bits = self.required_field_bits();
+        if field_bits >= required_bits {
+            Ok(())
+        } else {
+            Err(CollatzError::FieldTooSmall {
+                field_bits,
+                required_bits,
+            })
+        }
+    }
+
+    /// Binary representation (LSB first) of `value` over the N bit columns.
+    pub fn value_bits<'a>(&self, value: &'a BigUint) -> impl Iterator<Item = bool> + 'a {
+        (0..self.num_bits).map(move |i| value.bit(i as u64))
+    }
+
+    /// Value of limb `k` of `value`.
+    pub fn limb_value(&self, value: &BigUint, k: usize) -> i64 {
+        self.limb(k)
+            .rev()
+            .fold(0, |acc, i| 2 * acc + value.bit(i as u64) as i64)
+    }
+
+    /// `(limb_0 - 1) + 2 * (limb_1 + ... + limb_{L-1})` of `value`, which is zero iff `value` is 1.
+    /// It is linear in the bit columns, so the done flag can be tied to it with low-degree constraints.
+    pub fn done_sum(&self, value: &BigUint) -> i64 {
+        (1..self.num_limbs()).fold(self.limb_value(value, 0) - 1, |acc, k| {
+            acc + 2 * self.limb_value(value, k)
+        })
+    }
+
+    /// Offset carries `carry_k + CARRY_OFFSET`, for `1 <= k < num_limbs()`, of the transition from `current` to `next`.
+    pub fn carries(&self, current: &BigUint, next: &BigUint) -> Vec<u64> {
+        let is_odd = current.bit(0);
+        let mut carry = 0;
+        let mut carries = Vec::with_capacity(self.num_limbs().saturating_sub(1));
+        for k in 0..self.num_limbs() {
+            let limb = self.limb_value(current, k);
+            let target = match (is_odd, k) {
+                (true, 0) => 6 * limb + 2,
+                (true, _) => 6 * limb,
+                (false, _) => limb,
+            } + carry;
+            let remainder = target - 2 * self.limb_value(next, k);
+            if k + 1 == self.num_limbs() {
+                debug_assert_eq!(remainder, 0, "{next} is not the successor of {current}");
+            } else {
+                debug_assert_eq!(remainder % (1 << self.limb_bits), 0);
+                carry = remainder >> self.limb_bits;
+                carries.push((carry + CARRY_OFFSET as i64) as u64);
+            }
+        }
+        carries
+    }
+
+    /// Writes `row` into `out` (of length `width()`), converting each cell with `to_field`.
+    /// The done inverse is the only cell which is not an integer: it is computed with the field's `inverse`.
+    pub fn write_row<T>(
+        &self,
+        row: &CollatzRow,
+        out: &mut [T],
+        to_field: impl Fn(u64) -> T,
+        inverse: impl Fn(T) -> T,
+    ) {
+        for (i, bit) in self.value_bits(row.value).enumerate() {
+            out[self.bit(i)] = to_field(bit as u64);
+        }
+        out[self.step_counter()] = to_field(row.step as u64);
+        out[self.transition_flag()] = to_field(row.is_transition() as u64);
+
+        // Values of the sequence are positive, so the done sum is never negative
+        let done_sum = self.done_sum(row.value) as u64;
+        out[self.done_flag()] = to_field((done_sum == 0) as u64);
+        out[self.done_inverse()] = if done_sum == 0 {
+            to_field(0)
+        } else {
+            inverse(to_field(done_sum))
+        };
+
+        // Carry bits are only set on transition rows
+        for column in self.carry_columns() {
+            out[column] = to_field(0);
+        }
+        if let Some(previous) = row.previous {
+            for (k, carry) in (1..).zip(self.carries(previous, row.value)) {
+                for j in 0..CARRY_BITS {
+                    out[self.carry_bit(k, j)] = to_field(carry >> j & 1);
+                }
+            }
+        }
+    }
+
+    /// Decodes the cells of a trace row, read as integers with `cell`, for diagnostics.
+    pub fn decode_row(&self, cell: impl Fn(usize) -> u128) -> DecodedRow {
+        let value = (0..self.num_bits)
+            .rev()
+            .fold(BigUint::default(), |acc, i| acc * 2u32 + cell(self.bit(i)));
+        let carries = (1..self.num_limbs())
+            .map(|k| {
+                (0..CARRY_BITS)
+                    .rev()
+                    .try_fold(0, |acc, j| match cell(self.carry_bit(k, j)) {
+                        bit @ (0 | 1) => Some(2 * acc + bit as i64),
+                        _ => None,
+                    })
+            })
+            .map(|carry| carry.map(|carry| carry - CARRY_OFFSET as i64))
+            .collect();
+        DecodedRow {
+            value,
+            step_counter: cell(self.step_counter()),
+            transition_flag: cell(self.transition_flag()),
+            done_flag: cell(self.done_flag()),
+            carries,
+        }
+    }
+}
+
+/// The cells of a trace row, decoded into integers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodedRow {
+    /// Weighted sum of the bit cells, which is the row's value if they are binary.
+    pub value: BigUint,
+    pub step_counter: u128,
+    pub transition_flag: u128,
+    pub done_flag: u128,
+    /// Carry into each limb but the first, or `None` if its carry bit cells are not binary.
+    pub carries: Vec<Option<i64>>,
+}
+
+impl fmt::Display for DecodedRow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "value {}, step {}, transition {}, done {}",
+            self.value, self.step_counter, self.transition_flag, self.done_flag
+        )?;
+        for (k, carry) in (1..).zip(&self.carries) {
+            match carry {
+                Some(carry) => write!(f, ", carry into limb {k} {carry}")?,
+                None => write!(f, ", carry into limb {k} not binary")?,
+            }
+        }
+        Ok(())
+    }
+}
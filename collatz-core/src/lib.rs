@@ -0,0 +1,36 @@
+//! Backend-agnostic semantics of the Collatz AIRs.
+//!
+//! Both the Winterfell and the Plonky3 implementations consume this crate for:
+//! - computing the Collatz sequence of a starting value,
+//! - turning it into an honest, padded witness ([`CollatzWitness`]),
+//! - laying out each witness row as trace columns ([`TraceLayout`]),
+//! - the claim being proven ([`CollatzClaim`]) and the versioned container proofs are stored in ([`ProofContainer`]),
+//! - the interface both backends implement ([`CollatzBackend`]), and the errors they report ([`CollatzError`]),
+//! - estimating the security of their proofs ([`SecurityReport`]).
+//!
+//! Keeping these in one place ensures the two backends prove exactly the same statement.
+
+mod backend;
+mod claim;
+mod container;
+mod error;
+mod layout;
+mod security;
+mod sequence;
+mod witness;
+
+pub use num_bigint::BigUint;
+
+pub use backend::CollatzBackend;
+pub use claim::CollatzClaim;
+pub use container::{
+    Backend, FriParameters, ProofContainer, ProofFormatError, ProofParameters,
+    PROOF_FORMAT_VERSION, PROOF_MAGIC,
+};
+pub use error::CollatzError;
+pub use layout::{DecodedRow, TraceLayout, CARRY_BITS, CARRY_OFFSET, LIMB_BITS, WIDE_LIMB_BITS};
+pub use security::SecurityReport;
+pub use sequence::{
+    compute_collatz_sequence, CollatzValue, SequenceError, SequenceLimits, DEFAULT_MAX_STEPS,
+};
+pub use witness::{CollatzRow, CollatzWitness};
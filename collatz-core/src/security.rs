@@ -0,0 +1,43 @@
+/// Estimated security of a STARK proof with a FRI-based commitment scheme, in bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SecurityReport {
+    /// Under the usual conjecture on the soundness of FRI, as in the Winterfell and Plonky3 verifiers.
+    pub conjectured: u32,
+    /// Proven in the unique decoding regime of FRI, which is more conservative than the list decoding
+    /// regime.
+    pub proven: u32,
+}
+
+impl SecurityReport {
+    /// Estimates the security of proofs whose FRI challenges are drawn from a field of `field_bits` bits,
+    /// over a low-degree extension domain of `2^lde_domain_bits` points, with `num_queries` queries at rate
+    /// `2^-log_blowup`, `grinding_bits` of proof of work, and a hash with `collision_resistance` bits.
+    pub fn estimate(
+        field_bits: u32,
+        lde_domain_bits: u32,
+        log_blowup: u32,
+        num_queries: u32,
+        grinding_bits: u32,
+        collision_resistance: u32,
+    ) -> Self {
+        // Each query catches a cheating prover with probability 1 - rho, conjecturally
+        let query_security = log_blowup * num_queries + grinding_bits;
+        let conjectured = (field_bits.saturating_sub(lde_domain_bits))
+            .min(query_security)
+            .saturating_sub(1);
+
+        // Below the unique decoding radius, each query only catches a cheating prover with probability
+        // (1 - rho) / 2, and the commit phase of FRI errs with probability at most |D|^2 / |F|
+        let rate = 0.5f64.powi(log_blowup as i32);
+        let query_security =
+            (-(num_queries as f64) * ((1.0 + rate) / 2.0).log2()) as u32 + grinding_bits;
+        let proven = (field_bits.saturating_sub(2 * lde_domain_bits))
+            .min(query_security)
+            .saturating_sub(1);
+
+        Self {
+            conjectured: conjectured.min(collision_resistance),
+            proven: proven.min(collision_resistance),
+        }
+    }
+}
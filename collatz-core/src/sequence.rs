@@ -0,0 +1,270 @@
+use std::fmt;
+
+use num_bigint::BigUint;
+
+/// Default bound on the number of steps of a sequence, which keeps the trace within 2^20 rows.
+pub const DEFAULT_MAX_STEPS: usize = (1 << 20) - 1;
+
+/// Integer types the Collatz sequence can be computed in.
+pub trait CollatzValue: Clone + PartialEq {
+    fn is_zero(&self) -> bool;
+    fn is_one(&self) -> bool;
+    /// Number of bits in the binary representation of the value.
+    fn bit_length(&self) -> usize;
+    /// Applies the Collatz rule, or returns `None` if the result does not fit in the type.
+    fn checked_collatz_step(&self) -> Option<Self>;
+}
+
+macro_rules! impl_collatz_value {
+    ($($t:ty),*) => {$(
+        impl CollatzValue for $t {
+            fn is_zero(&self) -> bool {
+                *self == 0
+            }
+
+            fn is_one(&self) -> bool {
+                *self == 1
+            }
+
+            fn bit_length(&self) -> usize {
+                (<$t>::BITS - self.leading_zeros()) as usize
+            }
+
+            fn checked_collatz_step(&self) -> Option<Self> {
+                if self & 1 == 1 {
+                    self.checked_mul(3)?.checked_add(1)
+                } else {
+                    Some(self >> 1)
+                }
+            }
+        }
+    )*};
+}
+
+impl_collatz_value!(u32, u64, u128);
+
+impl CollatzValue for BigUint {
+    fn is_zero(&self) -> bool {
+        self.bits() == 0
+    }
+
+    fn is_one(&self) -> bool {
+        self.bits() == 1
+    }
+
+    fn bit_length(&self) -> usize {
+        self.bits() as usize
+    }
+
+    fn checked_collatz_step(&self) -> Option<Self> {
+        Some(if self.bit(0) {
+            3u32 * self + 1u32
+        } else {
+            self >> 1
+        })
+    }
+}
+
+/// Bounds enforced while computing a Collatz sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SequenceLimits {
+    /// Maximum number of bits of any element of the sequence, when the trace width is fixed.
+    pub max_bits: Option<usize>,
+    /// Maximum number of steps to reach 1.
+    pub max_steps: usize,
+}
+
+impl Default for SequenceLimits {
+    fn default() -> Self {
+        Self {
+            max_bits: None,
+            max_steps: DEFAULT_MAX_STEPS,
+        }
+    }
+}
+
+/// Reasons why no valid witness exists for a starting value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SequenceError {
+    /// The sequence starting at 0 never reaches 1.
+    ZeroInput,
+    /// The element following step `step` does not fit in the integer type.
+    Overflow { step: usize },
+    /// The element at step `step` has `bits` bits, more than the configured `max_bits`.
+    ExceedsBitWidth {
+        step: usize,
+        bits: usize,
+        max_bits: usize,
+    },
+    /// The sequence does not reach 1 within `max_steps` steps.
+    ExceedsStepBudget { max_steps: usize },
+}
+
+impl fmt::Display for SequenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SequenceError::ZeroInput => write!(f, "the starting value must be positive"),
+            SequenceError::Overflow { step } => {
+                write!(
+                    f,
+                    "the element after step {step} overflows the integer type"
+                )
+            }
+            SequenceError::ExceedsBitWidth {
+                step,
+                bits,
+                max_bits,
+            } => write!(
+                f,
+                "the element at step {step} has {bits} bits, more than the allowed {max_bits}"
+            ),
+            SequenceError::ExceedsStepBudget { max_steps } => {
+                write!(f, "the sequence does not reach 1 within {max_steps} steps")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SequenceError {}
+
+/// Computes the Collatz sequence starting from n until it reaches 1, within `limits`
+pub fn compute_collatz_sequence<T: CollatzValue>(
+    n: T,
+    limits: &SequenceLimits,
+) -> Result<Vec<T>, SequenceError> {
+    if n.is_zero() {
+        return Err(SequenceError::ZeroInput);
+    }
+
+    let mut sequence = Vec::new();
+    let mut current = n;
+
+    loop {
+        let step = sequence.len();
+        if let Some(max_bits) = limits.max_bits {
+            let bits = current.bit_length();
+            if bits > max_bits {
+                return Err(SequenceError::ExceedsBitWidth {
+                    step,
+                    bits,
+                    max_bits,
+                });
+            }
+        }
+        if current.is_one() {
+            break;
+        }
+        if step == limits.max_steps {
+            return Err(SequenceError::ExceedsStepBudget {
+                max_steps: limits.max_steps,
+            });
+        }
+
+        let next = current
+            .checked_collatz_step()
+            .ok_or(SequenceError::Overflow { step })?;
+        sequence.push(current);
+        current = next;
+    }
+    sequence.push(current);
+    Ok(sequence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(max_bits: Option<usize>, max_steps: usize) -> SequenceLimits {
+        SequenceLimits {
+            max_bits,
+            max_steps,
+        }
+    }
+
+    #[test]
+    fn computes_the_sequence_down_to_one() {
+        let sequence = compute_collatz_sequence(6u32, &SequenceLimits::default()).unwrap();
+        assert_eq!(sequence, [6, 3, 10, 5, 16, 8, 4, 2, 1]);
+        assert_eq!(
+            compute_collatz_sequence(BigUint::from(1u32), &SequenceLimits::default()).unwrap(),
+            [BigUint::from(1u32)]
+        );
+    }
+
+    #[test]
+    fn rejects_zero() {
+        assert_eq!(
+            compute_collatz_sequence(0u64, &SequenceLimits::default()),
+            Err(SequenceError::ZeroInput)
+        );
+        assert_eq!(
+            compute_collatz_sequence(BigUint::from(0u32), &SequenceLimits::default()),
+            Err(SequenceError::ZeroInput)
+        );
+    }
+
+    #[test]
+    fn reports_overflow_of_fixed_width_types() {
+        let limits = SequenceLimits::default();
+        assert_eq!(
+            compute_collatz_sequence(u32::MAX, &limits),
+            Err(SequenceError::Overflow { step: 0 })
+        );
+        assert_eq!(
+            compute_collatz_sequence(u64::MAX, &limits),
+            Err(SequenceError::Overflow { step: 0 })
+        );
+        assert_eq!(
+            compute_collatz_sequence(u128::MAX, &limits),
+            Err(SequenceError::Overflow { step: 0 })
+        );
+        // 2 * (2^31 - 1) halves without overflow, and only then overflows
+        assert_eq!(
+            compute_collatz_sequence(u32::MAX - 1, &limits),
+            Err(SequenceError::Overflow { step: 1 })
+        );
+        // The same values don't overflow as big integers
+        assert!(compute_collatz_sequence(BigUint::from(u32::MAX), &limits).is_ok());
+    }
+
+    #[test]
+    fn reports_elements_exceeding_the_bit_width() {
+        // 7 fits in 4 bits, but the next element, 22, needs 5
+        assert_eq!(
+            compute_collatz_sequence(7u32, &limits(Some(4), DEFAULT_MAX_STEPS)),
+            Err(SequenceError::ExceedsBitWidth {
+                step: 1,
+                bits: 5,
+                max_bits: 4,
+            })
+        );
+        assert_eq!(
+            compute_collatz_sequence(BigUint::from(16u32), &limits(Some(4), DEFAULT_MAX_STEPS)),
+            Err(SequenceError::ExceedsBitWidth {
+                step: 0,
+                bits: 5,
+                max_bits: 4,
+            })
+        );
+        assert!(compute_collatz_sequence(7u32, &limits(Some(6), DEFAULT_MAX_STEPS)).is_ok());
+    }
+
+    #[test]
+    fn reports_sequences_exceeding_the_step_budget() {
+        // 6 reaches 1 in exactly 8 steps
+        assert_eq!(
+            compute_collatz_sequence(6u32, &limits(None, 7)),
+            Err(SequenceError::ExceedsStepBudget { max_steps: 7 })
+        );
+        assert_eq!(
+            compute_collatz_sequence(6u32, &limits(None, 8))
+                .unwrap()
+                .len(),
+            9
+        );
+        assert_eq!(
+            compute_collatz_sequence(BigUint::from(27u32), &limits(None, 100)),
+            Err(SequenceError::ExceedsStepBudget { max_steps: 100 })
+        );
+    }
+}
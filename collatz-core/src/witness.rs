@@ -0,0 +1,106 @@
+use num_bigint::BigUint;
+
+use crate::sequence::{compute_collatz_sequence, SequenceError, SequenceLimits};
+
+/// A single row of the Collatz trace, before it is laid out into field elements.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CollatzRow<'a> {
+    /// The value of the sequence at this row.
+    pub value: &'a BigUint,
+    /// Number of Collatz steps applied so far.
+    pub step: u32,
+    /// The value this row was obtained from by applying the Collatz rule, or `None` for the
+    /// first row and the padding rows, which are not transitions.
+    pub previous: Option<&'a BigUint>,
+}
+
+impl CollatzRow<'_> {
+    pub fn is_transition(&self) -> bool {
+        self.previous.is_some()
+    }
+}
+
+/// The honest witness for a starting value: its Collatz sequence, together with the padding
+/// policy that extends it to a full trace.
+///
+/// Padding policy: the trace length is the next power of two of the sequence length, or of the
+/// backend's minimum trace length if it is longer (see [`CollatzWitness::with_min_trace_length`]), and
+/// the remaining rows repeat the final value `1` as non-transition rows, keeping the step counter fixed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CollatzWitness {
+    sequence: Vec<BigUint>,
+    min_trace_length: usize,
+}
+
+impl CollatzWitness {
+    /// Computes the witness for `starting_value`, failing if its sequence does not stay within `limits`.
+    pub fn new(
+        starting_value: impl Into<BigUint>,
+        limits: &SequenceLimits,
+    ) -> Result<Self, SequenceError> {
+        Ok(Self {
+            sequence: compute_collatz_sequence(starting_value.into(), limits)?,
+            min_trace_length: 1,
+        })
+    }
+
+    /// Pads the trace to at least `min_trace_length` rows, e.g. so that the short sequences of 1, 2 or 4
+    /// meet the minimum trace length of a backend's prover.
+    pub fn with_min_trace_length(mut self, min_trace_length: usize) -> Self {
+        self.min_trace_length = min_trace_length;
+        self
+    }
+
+    pub fn starting_value(&self) -> &BigUint {
+        &self.sequence[0]
+    }
+
+    /// The Collatz sequence, from the starting value down to 1.
+    pub fn sequence(&self) -> &[BigUint] {
+        &self.sequence
+    }
+
+    /// Number of steps it takes to reach 1.
+    pub fn steps(&self) -> u32 {
+        (self.sequence.len() - 1) as u32
+    }
+
+    /// Number of bits in the largest element of the sequence.
+    pub fn max_bits(&self) -> usize {
+        self.sequence
+            .iter()
+            .map(|value| value.bits() as usize)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Length of the padded trace.
+    pub fn trace_length(&self) -> usize {
+        self.sequence
+            .len()
+            .max(self.min_trace_length)
+            .next_power_of_two()
+    }
+
+    /// Iterates over all `trace_length()` rows of the padded trace.
+    pub fn rows(&self) -> impl Iterator<Item = CollatzRow<'_>> {
+        let steps = self.steps();
+        (0..self.trace_length()).map(move |i| match i {
+            0 => CollatzRow {
+                value: &self.sequence[0],
+                step: 0,
+                previous: None,
+            },
+            i if i as u32 <= steps => CollatzRow {
+                value: &self.sequence[i],
+                step: i as u32,
+                previous: Some(&self.sequence[i - 1]),
+            },
+            _ => CollatzRow {
+                value: &self.sequence[steps as usize],
+                step: steps,
+                previous: None,
+            },
+        })
+    }
+}
@@ -0,0 +1,304 @@
+//! Malicious provers: traces which bypass the honest trace generators, to check that the AIRs reject them.
+//!
+//! Each [`Attack`] forges a trace for a dishonest claim, or an honest claim with an invalid trace, and
+//! hands it to the backends' provers directly. Proving may fail (Plonky3 checks the constraints in debug
+//! builds) or produce a proof which does not verify; either way, the outcome must be a rejection.
+
+use std::ops::Neg;
+
+use collatz_core::{
+    compute_collatz_sequence, Backend, BigUint, CollatzClaim, CollatzError, CollatzWitness,
+    ProofContainer, SequenceLimits, TraceLayout, CARRY_BITS, CARRY_OFFSET,
+};
+use p3_field::{Field, PrimeCharacteristicRing};
+use p3_matrix::dense::RowMajorMatrix;
+use plonky3_collatz::config::{CollatzStarkConfig, Val};
+use winter_math::fields::f128::BaseElement;
+use winter_math::FieldElement;
+use winterfell::{Prover, TraceTable};
+use winterfell_collatz::config::{
+    default_proof_options, proof_parameters, Hasher, Primitives, SecurityPreset,
+};
+use winterfell_collatz::CollatzProver;
+
+use crate::{catch, Outcome};
+
+/// A trace, as integer cells, together with the claim it is proven against.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ForgedTrace {
+    pub layout: TraceLayout,
+    pub claim: CollatzClaim,
+    /// The rows of the trace, each of `layout.width()` cells. The done inverse cells are ignored: each
+    /// backend computes them in its own field from the other cells, as a prover would.
+    pub rows: Vec<Vec<u64>>,
+}
+
+impl ForgedTrace {
+    /// Lays out `sequence` as the honest generators would, without checking that it is a Collatz sequence,
+    /// and pairs it with `claim`. The trace is padded to the minimum trace length of both backends.
+    pub fn from_sequence(sequence: &[BigUint], claim: CollatzClaim) -> Self {
+        let num_bits = sequence.iter().map(|value| value.bits() as usize).max();
+        let layout = TraceLayout::new(num_bits.unwrap_or(0));
+        let last = sequence.len() - 1;
+        let min_trace_length =
+            winterfell_collatz::config::min_trace_length(&default_proof_options())
+                .max(CollatzStarkConfig::default().min_trace_length());
+        let rows = (0..sequence.len().max(min_trace_length).next_power_of_two())
+            .map(|i| {
+                let value = &sequence[i.min(last)];
+                let mut cells = vec![0; layout.width()];
+                for (j, bit) in layout.value_bits(value).enumerate() {
+                    cells[layout.bit(j)] = bit as u64;
+                }
+                cells[layout.step_counter()] = i.min(last) as u64;
+                cells[layout.done_flag()] = (layout.done_sum(value) == 0) as u64;
+                if (1..=last).contains(&i) {
+                    cells[layout.transition_flag()] = 1;
+                    for (k, carry) in (1..).zip(carries(&layout, &sequence[i - 1], value)) {
+                        for j in 0..CARRY_BITS {
+                            cells[layout.carry_bit(k, j)] = carry >> j & 1;
+                        }
+                    }
+                }
+                cells
+            })
+            .collect();
+        Self {
+            layout,
+            claim,
+            rows,
+        }
+    }
+
+    /// The honest trace and claim for `starting_value`, built like a forged one.
+    pub fn honest(starting_value: &BigUint) -> Self {
+        let witness = CollatzWitness::new(starting_value.clone(), &SequenceLimits::default())
+            .expect("Invalid starting value");
+        Self::from_sequence(witness.sequence(), CollatzClaim::from(&witness))
+    }
+
+    /// The padding rows, which repeat the final value.
+    fn padding(&self) -> std::ops::Range<usize> {
+        self.claim.steps_count as usize + 1..self.rows.len()
+    }
+
+    /// The trace over the Winterfell field.
+    pub fn winterfell_trace(&self) -> TraceTable<BaseElement> {
+        let cells = self.field_cells(BaseElement::from, |x| x.inv());
+        let width = self.layout.width();
+        let mut trace = TraceTable::new(width, self.rows.len());
+        trace.fill(
+            |state| state.copy_from_slice(&cells[..width]),
+            |i, state| state.copy_from_slice(&cells[(i + 1) * width..(i + 2) * width]),
+        );
+        trace
+    }
+
+    /// The trace over the Plonky3 field.
+    pub fn plonky3_trace(&self) -> RowMajorMatrix<Val> {
+        let cells = self.field_cells(Val::from_u64, |x| x.inverse());
+        RowMajorMatrix::new(cells, self.layout.width())
+    }
+
+    /// The cells in row-major order, converted with `to_field`, with the done inverses filled in.
+    pub(crate) fn field_cells<T: Copy + Neg<Output = T>>(
+        &self,
+        to_field: impl Fn(u64) -> T,
+        inverse: impl Fn(T) -> T,
+    ) -> Vec<T> {
+        let layout = &self.layout;
+        self.rows
+            .iter()
+            .flat_map(|row| {
+                let mut cells: Vec<T> = row.iter().map(|&cell| to_field(cell)).collect();
+                // The done sum of the cells, which need not be binary
+                let limb = |k: usize| {
+                    layout
+                        .limb(k)
+                        .rev()
+                        .fold(0, |acc, i| 2 * acc + row[layout.bit(i)] as i64)
+                };
+                let done_sum =
+                    (1..layout.num_limbs()).fold(limb(0) - 1, |acc, k| acc + 2 * limb(k));
+                cells[layout.done_inverse()] = if row[layout.done_flag()] != 0 || done_sum == 0 {
+                    to_field(0)
+                } else if done_sum < 0 {
+                    inverse(-to_field(done_sum.unsigned_abs()))
+                } else {
+                    inverse(to_field(done_sum as u64))
+                };
+                cells
+            })
+            .collect()
+    }
+}
+
+/// Offset carries of the limb-wise Collatz rule from `current` to `next`, truncated to `CARRY_BITS` bits.
+/// Unlike [`TraceLayout::carries`], `next` need not be the successor of `current`.
+fn carries(layout: &TraceLayout, current: &BigUint, next: &BigUint) -> Vec<u64> {
+    let is_odd = current.bit(0);
+    let mut carry = 0;
+    (0..layout.num_limbs().saturating_sub(1))
+        .map(|k| {
+            let limb = layout.limb_value(current, k);
+            let target = match (is_odd, k) {
+                (true, 0) => 6 * limb + 2,
+                (true, _) => 6 * limb,
+                (false, _) => limb,
+            } + carry;
+            carry = (target - 2 * layout.limb_value(next, k)) >> layout.limb_bits();
+            (carry + CARRY_OFFSET as i64) as u64 & ((1 << CARRY_BITS) - 1)
+        })
+        .collect()
+}
+
+/// Ways of cheating the AIRs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Attack {
+    /// The honest sequence, claimed to take one more step, with the step counter bumped on the final rows.
+    WrongStepCount,
+    /// A value written with a bit cell set to 2, keeping its weighted sum.
+    NonBinaryBit,
+    /// The sequence with its first step skipped, claimed to take one step less.
+    SkippedStep,
+    /// The sequence with its first step taken with the rule of the other parity.
+    WrongParity,
+    /// The sequence stopped one step before reaching 1.
+    NonOneFinalRow,
+    /// The honest trace with a padding row marked as a transition.
+    FlagToggledInPadding,
+    /// The sequence going once more around the `1 -> 4 -> 2 -> 1` cycle, claimed to take three more steps.
+    CyclingThroughOne,
+}
+
+impl Attack {
+    pub const ALL: [Attack; 7] = [
+        Attack::WrongStepCount,
+        Attack::NonBinaryBit,
+        Attack::SkippedStep,
+        Attack::WrongParity,
+        Attack::NonOneFinalRow,
+        Attack::FlagToggledInPadding,
+        Attack::CyclingThroughOne,
+    ];
+
+    /// Forges a trace from the sequence of `starting_value`, or returns `None` if the attack does not
+    /// apply to it (e.g. there are no padding rows to tamper with).
+    pub fn forge(&self, starting_value: &BigUint) -> Option<ForgedTrace> {
+        let sequence =
+            compute_collatz_sequence(starting_value.clone(), &SequenceLimits::default()).ok()?;
+        let steps = sequence.len() as u32 - 1;
+        let claim = |steps_count| CollatzClaim::new(starting_value.clone(), steps_count);
+        let honest = ForgedTrace::from_sequence(&sequence, claim(steps));
+
+        match self {
+            Attack::WrongStepCount => {
+                let mut forged = honest;
+                forged.claim.steps_count += 1;
+                let counter = forged.layout.step_counter();
+                for row in &mut forged.rows[steps as usize..] {
+                    row[counter] += 1;
+                }
+                Some(forged)
+            }
+            Attack::NonBinaryBit => {
+                let mut forged = honest;
+                let layout = forged.layout;
+                // 2 * 2^j == 2^(j+1), so bits (0, 1) at (j, j+1) can be replaced by (2, 0) within a limb
+                let (row, j) = (0..forged.rows.len()).find_map(|row| {
+                    let cells = &forged.rows[row];
+                    (0..layout.num_limbs())
+                        .flat_map(|k| layout.limb(k).zip(layout.limb(k).skip(1)))
+                        .find(|&(j, next)| {
+                            cells[layout.bit(j)] == 0 && cells[layout.bit(next)] == 1
+                        })
+                        .map(|(j, _)| (row, j))
+                })?;
+                forged.rows[row][layout.bit(j)] = 2;
+                forged.rows[row][layout.bit(j + 1)] = 0;
+                Some(forged)
+            }
+            Attack::SkippedStep => {
+                if steps < 2 {
+                    return None;
+                }
+                let mut skipped = sequence;
+                skipped.remove(1);
+                Some(ForgedTrace::from_sequence(&skipped, claim(steps - 1)))
+            }
+            Attack::WrongParity => {
+                let start = &sequence[0];
+                let next = if start.bit(0) {
+                    start >> 1
+                } else {
+                    3u32 * start + 1u32
+                };
+                let mut forged = vec![start.clone()];
+                forged.extend(compute_collatz_sequence(next, &SequenceLimits::default()).ok()?);
+                let steps_count = forged.len() as u32 - 1;
+                Some(ForgedTrace::from_sequence(&forged, claim(steps_count)))
+            }
+            Attack::NonOneFinalRow => {
+                if steps < 2 {
+                    return None;
+                }
+                let stopped = &sequence[..sequence.len() - 1];
+                Some(ForgedTrace::from_sequence(stopped, claim(steps - 1)))
+            }
+            Attack::FlagToggledInPadding => {
+                let mut forged = honest;
+                let row = forged.padding().next()?;
+                let flag = forged.layout.transition_flag();
+                forged.rows[row][flag] = 1;
+                Some(forged)
+            }
+            Attack::CyclingThroughOne => {
+                let mut cycled = sequence;
+                cycled.extend([4u32, 2, 1].map(BigUint::from));
+                Some(ForgedTrace::from_sequence(&cycled, claim(steps + 3)))
+            }
+        }
+    }
+}
+
+/// Proves `forged` with the Winterfell prover and verifies the proof against its claim.
+pub fn winterfell_outcome(forged: &ForgedTrace) -> Outcome {
+    let options = default_proof_options();
+    let result = catch(|| {
+        let trace = forged.winterfell_trace();
+        let prover = CollatzProver::<Hasher>::new(
+            options.clone(),
+            forged.claim.starting_value.clone(),
+            forged.claim.steps_count,
+        );
+        let proof = prover.prove(trace).map_err(|error| error.to_string())?;
+        let container = ProofContainer {
+            backend: Backend::Winterfell,
+            parameters: proof_parameters(Primitives::default(), &options, false),
+            claim: forged.claim.clone(),
+            num_bits: forged.layout.num_bits() as u32,
+            proof: proof.to_bytes(),
+        };
+        let min_security = SecurityPreset::default().min_conjectured_security();
+        winterfell_collatz::verify(&container, min_security).map_err(|error| error.to_string())
+    });
+    match result {
+        Ok(()) => Outcome::Accepted,
+        Err(reason) => Outcome::Rejected(reason),
+    }
+}
+
+/// Proves `forged` with the Plonky3 prover and verifies the proof against its claim.
+pub fn plonky3_outcome(forged: &ForgedTrace) -> Outcome {
+    let trace = forged.plonky3_trace();
+    let config = CollatzStarkConfig::default();
+    let result = catch(|| {
+        let container =
+            plonky3_collatz::prove_trace(trace, forged.layout, forged.claim.clone(), &config)?;
+        Ok::<_, CollatzError>(plonky3_collatz::verify(&container, &config)?)
+    });
+    match result {
+        Ok(()) => Outcome::Accepted,
+        Err(reason) => Outcome::Rejected(reason),
+    }
+}
@@ -0,0 +1,287 @@
+//! Differential testing: the Winterfell and the Plonky3 AIRs must accept exactly the same statements.
+//!
+//! For each starting value, the harness
+//! - builds the honest trace with both backends and compares them cell by cell, up to the padding each
+//!   backend adds to meet its minimum trace length,
+//! - proves the true claim with both backends, and verifies the proofs against the true claim and
+//!   against tampered claims and proofs (see [`Case`]),
+//!
+//! and stops at the first divergence between the backends, or from the expected outcome.
+
+use std::fmt;
+
+use collatz_core::{
+    BigUint, CollatzBackend, CollatzClaim, CollatzWitness, ProofContainer, SequenceLimits,
+};
+use p3_field::PrimeField32;
+use p3_matrix::Matrix;
+use plonky3_collatz::config::{CollatzStarkConfig, Val};
+use plonky3_collatz::{generate_collatz_trace, Plonky3Backend};
+use winter_math::StarkField;
+use winterfell::Trace;
+use winterfell_collatz::config::{default_proof_options, Hasher};
+use winterfell_collatz::{CollatzProver, WinterfellBackend};
+
+use crate::{catch, Outcome};
+
+/// A statement checked by both backends, derived from the honest claim and proof.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Case {
+    /// The honest proof against the true claim.
+    Honest,
+    /// The honest proof against a claim with one more step.
+    StepsPlusOne,
+    /// The honest proof against a claim with one less step.
+    StepsMinusOne,
+    /// The honest proof against the next starting value, with the same number of steps.
+    NextStart,
+    /// The honest proof with a byte flipped in its middle, against the true claim.
+    FlippedProofByte,
+}
+
+impl Case {
+    pub const ALL: [Case; 5] = [
+        Case::Honest,
+        Case::StepsPlusOne,
+        Case::StepsMinusOne,
+        Case::NextStart,
+        Case::FlippedProofByte,
+    ];
+
+    /// Only the honest proof of the true claim may be accepted.
+    pub fn expected_to_verify(&self) -> bool {
+        *self == Case::Honest
+    }
+
+    /// The claim and proof checked in this case, or `None` if the case does not apply.
+    fn tamper(
+        &self,
+        claim: &CollatzClaim,
+        proof: &ProofContainer,
+    ) -> Option<(CollatzClaim, ProofContainer)> {
+        let mut claim = claim.clone();
+        let mut proof = proof.clone();
+        match self {
+            Case::Honest => {}
+            Case::StepsPlusOne => claim.steps_count += 1,
+            Case::StepsMinusOne => claim.steps_count = claim.steps_count.checked_sub(1)?,
+            Case::NextStart => claim.starting_value += 1u32,
+            Case::FlippedProofByte => {
+                let middle = proof.proof.len() / 2;
+                *proof.proof.get_mut(middle)? ^= 1;
+            }
+        }
+        Some((claim, proof))
+    }
+}
+
+/// The first disagreement found by [`run`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Divergence {
+    /// The honest traces have different widths.
+    TraceShape {
+        starting_value: BigUint,
+        /// `(rows, columns)` of each trace.
+        winterfell: (usize, usize),
+        plonky3: (usize, usize),
+    },
+    /// The honest traces differ in a cell, where the rows past the end of the shorter trace are compared
+    /// against its last row.
+    TraceCell {
+        starting_value: BigUint,
+        row: usize,
+        column: usize,
+        winterfell: u128,
+        plonky3: u128,
+    },
+    /// The backends disagree on a statement.
+    Outcome {
+        claim: CollatzClaim,
+        case: Case,
+        winterfell: Outcome,
+        plonky3: Outcome,
+    },
+    /// Both backends agree on a statement, but not with the expected outcome.
+    Unexpected {
+        claim: CollatzClaim,
+        case: Case,
+        outcome: Outcome,
+    },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Divergence::TraceShape {
+                starting_value,
+                winterfell,
+                plonky3,
+            } => write!(
+                f,
+                "traces for {starting_value} have shapes {winterfell:?} (winterfell) and {plonky3:?} (plonky3)"
+            ),
+            Divergence::TraceCell {
+                starting_value,
+                row,
+                column,
+                winterfell,
+                plonky3,
+            } => write!(
+                f,
+                "traces for {starting_value} differ at row {row}, column {column}: {winterfell} (winterfell) vs {plonky3} (plonky3)"
+            ),
+            Divergence::Outcome {
+                claim,
+                case,
+                winterfell,
+                plonky3,
+            } => write!(
+                f,
+                "{case:?} for {} in {} steps: winterfell {winterfell}, plonky3 {plonky3}",
+                claim.starting_value, claim.steps_count
+            ),
+            Divergence::Unexpected {
+                claim,
+                case,
+                outcome,
+            } => write!(
+                f,
+                "{case:?} for {} in {} steps: both backends {outcome}",
+                claim.starting_value, claim.steps_count
+            ),
+        }
+    }
+}
+
+/// Checks every starting value in turn, returning how many were checked, or the first divergence.
+pub fn run(starting_values: impl IntoIterator<Item = BigUint>) -> Result<usize, Divergence> {
+    let winterfell = WinterfellBackend::default();
+    let plonky3 = Plonky3Backend::new();
+    let mut checked = 0;
+    for starting_value in starting_values {
+        compare_traces(&starting_value)?;
+        compare_outcomes(&winterfell, &plonky3, &starting_value)?;
+        checked += 1;
+    }
+    Ok(checked)
+}
+
+/// Compares the honest traces of both backends, except for the inverse witness of the done flag,
+/// which depends on the field. The backends pad to different minimum lengths, so the shorter trace is
+/// extended with copies of its last row, a padding row.
+pub fn compare_traces(starting_value: &BigUint) -> Result<(), Divergence> {
+    let limits = SequenceLimits::default();
+    let witness =
+        CollatzWitness::new(starting_value.clone(), &limits).expect("Invalid starting value");
+
+    let winterfell_trace = CollatzProver::<Hasher>::new(
+        default_proof_options(),
+        starting_value.clone(),
+        witness.steps(),
+    )
+    .build_trace()
+    .expect("Invalid starting value");
+    let (plonky3_trace, layout, _) = generate_collatz_trace::<Val>(
+        starting_value,
+        &limits,
+        CollatzStarkConfig::default().min_trace_length(),
+    )
+    .expect("Invalid starting value");
+
+    let winterfell_shape = (winterfell_trace.length(), winterfell_trace.width());
+    let plonky3_shape = (plonky3_trace.height(), plonky3_trace.width());
+    if winterfell_shape.1 != plonky3_shape.1 {
+        return Err(Divergence::TraceShape {
+            starting_value: starting_value.clone(),
+            winterfell: winterfell_shape,
+            plonky3: plonky3_shape,
+        });
+    }
+
+    let columns = winterfell_shape.1;
+    for row in 0..winterfell_shape.0.max(plonky3_shape.0) {
+        let (winterfell_row, plonky3_row) = (
+            row.min(winterfell_shape.0 - 1),
+            row.min(plonky3_shape.0 - 1),
+        );
+        for column in (0..columns).filter(|&column| column != layout.done_inverse()) {
+            let winterfell = winterfell_trace.get(column, winterfell_row).as_int();
+            let plonky3 =
+                plonky3_trace.values[plonky3_row * columns + column].as_canonical_u32() as u128;
+            if winterfell != plonky3 {
+                return Err(Divergence::TraceCell {
+                    starting_value: starting_value.clone(),
+                    row,
+                    column,
+                    winterfell,
+                    plonky3,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Proves the true claim about `starting_value` with both backends, and checks that they agree on
+/// every [`Case`], as expected.
+pub fn compare_outcomes<W, P>(
+    winterfell: &W,
+    plonky3: &P,
+    starting_value: &BigUint,
+) -> Result<(), Divergence>
+where
+    W: CollatzBackend<Proof = ProofContainer>,
+    P: CollatzBackend<Proof = ProofContainer>,
+{
+    let witness = CollatzWitness::new(starting_value.clone(), &SequenceLimits::default())
+        .expect("Invalid starting value");
+    let claim = CollatzClaim::from(&witness);
+
+    let winterfell_outcomes = outcomes(winterfell, &claim);
+    let plonky3_outcomes = outcomes(plonky3, &claim);
+    for ((case, winterfell), (_, plonky3)) in winterfell_outcomes.into_iter().zip(plonky3_outcomes)
+    {
+        let (Some(winterfell), Some(plonky3)) = (winterfell, plonky3) else {
+            continue;
+        };
+        if winterfell.is_accepted() != plonky3.is_accepted() {
+            return Err(Divergence::Outcome {
+                claim,
+                case,
+                winterfell,
+                plonky3,
+            });
+        }
+        if winterfell.is_accepted() != case.expected_to_verify() {
+            return Err(Divergence::Unexpected {
+                claim,
+                case,
+                outcome: winterfell,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Outcome of every [`Case`] on `backend`, or `None` for the cases which do not apply to `claim`.
+fn outcomes<B>(backend: &B, claim: &CollatzClaim) -> Vec<(Case, Option<Outcome>)>
+where
+    B: CollatzBackend<Proof = ProofContainer>,
+{
+    let proof = catch(|| backend.prove(claim));
+    Case::ALL
+        .into_iter()
+        .map(|case| {
+            let outcome = match &proof {
+                Ok(proof) => case.tamper(claim, proof).map(|(claim, proof)| {
+                    match catch(|| backend.verify(&claim, &proof)) {
+                        Ok(()) => Outcome::Accepted,
+                        Err(reason) => Outcome::Rejected(reason),
+                    }
+                }),
+                Err(reason) => Some(Outcome::Rejected(format!("proving failed: {reason}"))),
+            };
+            (case, outcome)
+        })
+        .collect()
+}
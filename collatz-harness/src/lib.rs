@@ -0,0 +1,52 @@
+//! Test harnesses checking the two backends against each other and against dishonest provers.
+
+use std::any::Any;
+use std::fmt;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+pub mod adversarial;
+pub mod differential;
+pub mod underconstraint;
+
+/// Whether a backend accepted a statement.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Accepted,
+    /// Proving or verification failed, or panicked, for the given reason.
+    Rejected(String),
+}
+
+impl Outcome {
+    pub fn is_accepted(&self) -> bool {
+        *self == Outcome::Accepted
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Accepted => write!(f, "accepted"),
+            Outcome::Rejected(reason) => write!(f, "rejected ({reason})"),
+        }
+    }
+}
+
+/// Runs `f`, turning both its errors and its panics into a description of the failure.
+/// Provers and AIRs assert on some malformed inputs, which counts as a rejection here.
+pub(crate) fn catch<T, E: fmt::Display>(f: impl FnOnce() -> Result<T, E>) -> Result<T, String> {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(error)) => Err(error.to_string()),
+        Err(panic) => Err(format!("panicked: {}", panic_message(&*panic))),
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
@@ -0,0 +1,255 @@
+//! Under-constraint detection: perturbs a valid trace and evaluates the AIRs' constraints directly, without
+//! proving, to find perturbations which still satisfy every constraint.
+//!
+//! Every cell is perturbed on its own, and every pair of cells within two consecutive rows (the window the
+//! constraints see) is perturbed together. Only the rows whose constraints can see a perturbed cell are
+//! re-evaluated, but the pairs still make the search quadratic in the width: use short sequences.
+
+use std::fmt;
+use std::ops::{Add, Sub};
+
+use collatz_core::{Backend, TraceLayout};
+use p3_field::{Field, PrimeCharacteristicRing};
+use plonky3_collatz::config::Val;
+use plonky3_collatz::debug::failing_constraints;
+use winter_math::fields::f128::BaseElement;
+use winter_math::FieldElement;
+use winterfell::{Air, EvaluationFrame, TraceInfo};
+use winterfell_collatz::config::default_proof_options;
+use winterfell_collatz::{CollatzAir, PublicInputs};
+
+use crate::adversarial::ForgedTrace;
+
+/// A change to a single cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    Zero,
+    One,
+    Increment,
+    Decrement,
+}
+
+impl Change {
+    const SINGLE: [Change; 4] = [
+        Change::Zero,
+        Change::One,
+        Change::Increment,
+        Change::Decrement,
+    ];
+    const PAIRED: [Change; 2] = [Change::Increment, Change::Decrement];
+
+    fn apply<T: Copy + Add<Output = T> + Sub<Output = T>>(self, value: T, zero: T, one: T) -> T {
+        match self {
+            Change::Zero => zero,
+            Change::One => one,
+            Change::Increment => value + one,
+            Change::Decrement => value - one,
+        }
+    }
+}
+
+/// A change to the cell at `row` and `column`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CellChange {
+    pub row: usize,
+    pub column: usize,
+    pub change: Change,
+}
+
+/// Changes to a valid trace which keep every constraint satisfied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Perturbation(pub Vec<CellChange>);
+
+impl fmt::Display for Perturbation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, cell) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(
+                f,
+                "{:?} at row {}, column {}",
+                cell.change, cell.row, cell.column
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The trace handed to the detector does not satisfy the constraints of `backend` to begin with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnsatisfiedTrace {
+    pub backend: Backend,
+    /// First row whose constraints (over the window starting at that row) do not hold.
+    pub row: usize,
+}
+
+impl fmt::Display for UnsatisfiedTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the trace does not satisfy the {} constraints at row {}",
+            self.backend, self.row
+        )
+    }
+}
+
+impl std::error::Error for UnsatisfiedTrace {}
+
+/// Perturbations of `trace` which satisfy every transition constraint and assertion of the Winterfell AIR.
+pub fn winterfell_survivors(trace: &ForgedTrace) -> Result<Vec<Perturbation>, UnsatisfiedTrace> {
+    let width = trace.layout.width();
+    let length = trace.rows.len();
+    let public_inputs =
+        PublicInputs::from((trace.claim.starting_value.clone(), trace.claim.steps_count));
+    let air = CollatzAir::<BaseElement>::new(
+        TraceInfo::new(width, length),
+        public_inputs,
+        default_proof_options(),
+    );
+
+    let mut assertions = vec![Vec::new(); length];
+    for assertion in air.get_assertions() {
+        assertion.apply(length, |step, value| {
+            assertions[step].push((assertion.column(), value))
+        });
+    }
+    let num_constraints = air.context().num_main_transition_constraints();
+
+    // The last row only has assertions: Winterfell does not apply transition constraints across the wrap-around
+    let satisfied = |cells: &[BaseElement], row: usize| {
+        let current = &cells[row * width..(row + 1) * width];
+        if assertions[row]
+            .iter()
+            .any(|&(column, value)| current[column] != value)
+        {
+            return false;
+        }
+        if row + 1 == length {
+            return true;
+        }
+        let next = &cells[(row + 1) * width..(row + 2) * width];
+        let frame = EvaluationFrame::from_rows(current.to_vec(), next.to_vec());
+        let mut result = vec![BaseElement::ZERO; num_constraints];
+        air.evaluate_transition(&frame, &air.periodic_values(row), &mut result);
+        result.iter().all(|&value| value == BaseElement::ZERO)
+    };
+
+    let cells = trace.field_cells(BaseElement::from, |x| x.inv());
+    survivors(
+        Backend::Winterfell,
+        &trace.layout,
+        cells,
+        (BaseElement::ZERO, BaseElement::ONE),
+        satisfied,
+    )
+}
+
+/// Perturbations of `trace` which satisfy every constraint of the Plonky3 AIR.
+pub fn plonky3_survivors(trace: &ForgedTrace) -> Result<Vec<Perturbation>, UnsatisfiedTrace> {
+    let width = trace.layout.width();
+    let length = trace.rows.len();
+    let air = plonky3_collatz::CollatzAir::new::<Val>(trace.layout)
+        .expect("The trace is too wide for the Plonky3 field");
+    let public_values = air
+        .public_values::<Val>(&trace.claim.starting_value, trace.claim.steps_count)
+        .expect("The starting value does not fit in the trace");
+
+    // Like the prover, the window of the last row wraps around to the first row
+    let satisfied = |cells: &[Val], row: usize| {
+        let next_row = (row + 1) % length;
+        failing_constraints(
+            &air,
+            &cells[row * width..(row + 1) * width],
+            &cells[next_row * width..(next_row + 1) * width],
+            &public_values,
+            row,
+            length,
+        )
+        .is_ok_and(|failed| failed.is_empty())
+    };
+
+    let cells = trace.field_cells(Val::from_u64, |x| x.inverse());
+    survivors(
+        Backend::Plonky3,
+        &trace.layout,
+        cells,
+        (Val::ZERO, Val::ONE),
+        satisfied,
+    )
+}
+
+/// Applies every single-cell and paired perturbation to `cells`, keeping those after which `satisfied`
+/// still holds on every row. `satisfied(cells, row)` checks the constraints over the window starting at `row`.
+fn survivors<T: Copy + PartialEq + Add<Output = T> + Sub<Output = T>>(
+    backend: Backend,
+    layout: &TraceLayout,
+    mut cells: Vec<T>,
+    (zero, one): (T, T),
+    satisfied: impl Fn(&[T], usize) -> bool,
+) -> Result<Vec<Perturbation>, UnsatisfiedTrace> {
+    let width = layout.width();
+    let length = cells.len() / width;
+    if let Some(row) = (0..length).find(|&row| !satisfied(&cells, row)) {
+        return Err(UnsatisfiedTrace { backend, row });
+    }
+
+    // A cell is seen by the window of its own row, and by that of the previous row
+    let holds_around = |cells: &[T], changes: &[CellChange]| {
+        changes.iter().all(|cell| {
+            satisfied(cells, cell.row) && satisfied(cells, (cell.row + length - 1) % length)
+        })
+    };
+    let position = |index: usize| (index / width, index % width);
+
+    let mut found = Vec::new();
+    for index in 0..cells.len() {
+        let (row, column) = position(index);
+        let original = cells[index];
+        for change in Change::SINGLE {
+            cells[index] = change.apply(original, zero, one);
+            let changes = [CellChange {
+                row,
+                column,
+                change,
+            }];
+            if cells[index] != original && holds_around(&cells, &changes) {
+                found.push(Perturbation(changes.to_vec()));
+            }
+        }
+        cells[index] = original;
+    }
+
+    for first in 0..cells.len() {
+        let (first_row, first_column) = position(first);
+        // The second cell is in the same row as the first one, or in the next row
+        for second in first + 1..cells.len().min((first_row + 2) * width) {
+            let (second_row, second_column) = position(second);
+            let (first_original, second_original) = (cells[first], cells[second]);
+            for first_change in Change::PAIRED {
+                for second_change in Change::PAIRED {
+                    cells[first] = first_change.apply(first_original, zero, one);
+                    cells[second] = second_change.apply(second_original, zero, one);
+                    let changes = [
+                        CellChange {
+                            row: first_row,
+                            column: first_column,
+                            change: first_change,
+                        },
+                        CellChange {
+                            row: second_row,
+                            column: second_column,
+                            change: second_change,
+                        },
+                    ];
+                    if holds_around(&cells, &changes) {
+                        found.push(Perturbation(changes.to_vec()));
+                    }
+                }
+            }
+            cells[first] = first_original;
+            cells[second] = second_original;
+        }
+    }
+    Ok(found)
+}
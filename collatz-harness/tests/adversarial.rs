@@ -0,0 +1,136 @@
+use collatz_core::{BigUint, CollatzBackend, CollatzClaim, CollatzError, ProofContainer};
+use collatz_harness::adversarial::{plonky3_outcome, winterfell_outcome, Attack, ForgedTrace};
+use collatz_harness::Outcome;
+use plonky3_collatz::config::Val;
+use plonky3_collatz::debug::Constraint;
+use plonky3_collatz::CollatzAir;
+use plonky3_collatz::Plonky3Backend;
+use winterfell_collatz::config::{default_proof_options, Hasher};
+use winterfell_collatz::{CollatzProver, WinterfellBackend};
+
+const STARTING_VALUES: [u32; 4] = [6, 7, 27, 97];
+
+#[test]
+fn honest_traces_are_accepted() {
+    for start in STARTING_VALUES.map(BigUint::from) {
+        let honest = ForgedTrace::honest(&start);
+        assert_eq!(winterfell_outcome(&honest), Outcome::Accepted, "{start}");
+        assert_eq!(plonky3_outcome(&honest), Outcome::Accepted, "{start}");
+    }
+}
+
+#[test]
+fn forged_traces_are_rejected() {
+    for attack in Attack::ALL {
+        let mut forged_count = 0;
+        for start in STARTING_VALUES.map(BigUint::from) {
+            let Some(forged) = attack.forge(&start) else {
+                continue;
+            };
+            let winterfell = winterfell_outcome(&forged);
+            assert!(
+                !winterfell.is_accepted(),
+                "{attack:?} on {start}: winterfell {winterfell}"
+            );
+            let plonky3 = plonky3_outcome(&forged);
+            assert!(
+                !plonky3.is_accepted(),
+                "{attack:?} on {start}: plonky3 {plonky3}"
+            );
+            forged_count += 1;
+        }
+        assert!(
+            forged_count > 0,
+            "{attack:?} applies to none of the starting values"
+        );
+    }
+}
+
+#[test]
+fn plonky3_diagnostics_name_the_failing_constraints() {
+    // 27 = 0b11011: the forged first row sets bits 2 and 3 to (2, 0), which keeps its value
+    let start = BigUint::from(27u32);
+    let forged = Attack::NonBinaryBit.forge(&start).unwrap();
+    let air = CollatzAir::new::<Val>(forged.layout).unwrap();
+    let public_values = air
+        .public_values::<Val>(&start, forged.claim.steps_count)
+        .unwrap();
+
+    let failures =
+        plonky3_collatz::check_trace(&air, &forged.plonky3_trace(), &public_values).unwrap();
+    let failed: Vec<_> = failures
+        .iter()
+        .map(|failure| (failure.row, failure.constraint))
+        .collect();
+    assert_eq!(
+        failed,
+        [
+            (0, Constraint::FirstRowBit(2)),
+            (0, Constraint::FirstRowBit(3))
+        ]
+    );
+    assert_eq!(failures[0].local.value, start);
+}
+
+#[test]
+fn winterfell_diagnostics_name_the_failing_constraints() {
+    let start = BigUint::from(27u32);
+    let forged = Attack::NonBinaryBit.forge(&start).unwrap();
+    let prover = CollatzProver::<Hasher>::new(
+        default_proof_options(),
+        start.clone(),
+        forged.claim.steps_count,
+    );
+
+    let failures = prover.check_trace(&forged.winterfell_trace()).unwrap();
+    let failed: Vec<_> = failures
+        .iter()
+        .map(|failure| (failure.row, failure.constraint))
+        .collect();
+    assert_eq!(
+        failed,
+        [
+            (
+                0,
+                winterfell_collatz::debug::Constraint::Assertion {
+                    column: 2,
+                    expected: 0
+                }
+            ),
+            (
+                0,
+                winterfell_collatz::debug::Constraint::Assertion {
+                    column: 3,
+                    expected: 1
+                }
+            )
+        ]
+    );
+    assert_eq!(failures[0].current.value, start);
+}
+
+#[test]
+fn bad_inputs_and_hostile_proofs_are_errors() {
+    let backends: [&dyn CollatzBackend<Proof = ProofContainer>; 2] =
+        [&WinterfellBackend::default(), &Plonky3Backend::new()];
+    for backend in backends {
+        let zero = CollatzClaim::new(BigUint::from(0u32), 0);
+        assert!(matches!(
+            backend.prove(&zero),
+            Err(CollatzError::InvalidInput(_))
+        ));
+
+        let claim = CollatzClaim::new(BigUint::from(27u32), 111);
+        let proof = backend.prove(&claim).unwrap();
+        // Too few bit columns for the starting value, more than the proof's trace has, and so many that
+        // allocating the public values before checking them against the proof would exhaust the memory
+        for num_bits in [1, 10_000, u32::MAX] {
+            let mut hostile = proof.clone();
+            hostile.num_bits = num_bits;
+            assert!(matches!(
+                backend.verify(&claim, &hostile),
+                Err(CollatzError::Verification(_))
+            ));
+        }
+    }
+}
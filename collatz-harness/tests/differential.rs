@@ -0,0 +1,35 @@
+use collatz_core::{BigUint, CollatzBackend, CollatzClaim};
+use collatz_harness::differential;
+use plonky3_collatz::Plonky3Backend;
+use winterfell_collatz::WinterfellBackend;
+
+#[test]
+fn backends_agree_on_small_starting_values() {
+    let checked = differential::run((1u32..=32).map(BigUint::from))
+        .unwrap_or_else(|divergence| panic!("{divergence}"));
+    assert_eq!(checked, 32);
+}
+
+#[test]
+fn backends_agree_on_long_and_wide_sequences() {
+    let starting_values = [
+        BigUint::from(27u32),
+        BigUint::from(97u32),
+        BigUint::from(871u32),
+        BigUint::from(u64::MAX) + 2u32,
+    ];
+    differential::run(starting_values).unwrap_or_else(|divergence| panic!("{divergence}"));
+}
+
+#[test]
+fn sequences_shorter_than_the_minimum_trace_length_are_provable() {
+    let winterfell = WinterfellBackend::default();
+    let plonky3 = Plonky3Backend::new();
+    for (start, steps) in [(1u32, 0), (2, 1), (4, 2)] {
+        let claim = CollatzClaim::new(start, steps);
+        let proof = winterfell.prove(&claim).unwrap();
+        winterfell.verify(&claim, &proof).unwrap();
+        let proof = plonky3.prove(&claim).unwrap();
+        plonky3.verify(&claim, &proof).unwrap();
+    }
+}
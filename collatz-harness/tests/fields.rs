@@ -0,0 +1,135 @@
+use collatz_core::{
+    BigUint, CollatzBackend, CollatzClaim, CollatzWitness, SequenceLimits, TraceLayout, LIMB_BITS,
+    WIDE_LIMB_BITS,
+};
+use plonky3_collatz::config::{
+    CollatzStarkConfig, ExtensionDegree, FieldChoice, HashChoice, SecurityPreset,
+};
+use plonky3_collatz::{verify_min_security, Plonky3Backend, VerifyError};
+use winterfell_collatz::config::{
+    proof_parameters, FieldChoice as WinterfellField, HashChoice as WinterfellHash, Primitives,
+    SecurityPreset as WinterfellPreset,
+};
+use winterfell_collatz::WinterfellBackend;
+
+fn claim(starting_value: BigUint) -> CollatzClaim {
+    let witness = CollatzWitness::new(starting_value, &SequenceLimits::default()).unwrap();
+    CollatzClaim::from(&witness)
+}
+
+/// Claims with one or several limbs, with values beyond 64 bits.
+fn claims() -> impl Iterator<Item = CollatzClaim> {
+    let large: BigUint = "123456789012345678901234567".parse().unwrap();
+    [1u32, 6, 27]
+        .map(BigUint::from)
+        .into_iter()
+        .chain([large])
+        .map(claim)
+}
+
+#[test]
+fn plonky3_proves_the_same_claims_on_every_field() {
+    let testing = CollatzStarkConfig::from_preset(SecurityPreset::Testing);
+    for claim in claims() {
+        for field in FieldChoice::ALL {
+            for &hash in field.hashes() {
+                let config = testing.with_field(field).with_hash(hash);
+                let backend = Plonky3Backend::new().with_config(config);
+                let proof = backend.prove(&claim).unwrap();
+                assert_eq!(proof.parameters, config.proof_parameters());
+                backend
+                    .verify(&claim, &proof)
+                    .unwrap_or_else(|error| panic!("{config:?}: {error}"));
+
+                verify_min_security(&proof, 0)
+                    .unwrap_or_else(|error| panic!("{config:?}: {error}"));
+                assert!(matches!(
+                    verify_min_security(&proof, 128),
+                    Err(VerifyError::InsufficientSecurity { .. })
+                ));
+
+                // A proof over one field does not verify over another
+                let other = FieldChoice::ALL.into_iter().find(|&other| other != field);
+                let other = Plonky3Backend::new().with_config(testing.with_field(other.unwrap()));
+                assert!(other.verify(&claim, &proof).is_err(), "{config:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn plonky3_checks_goldilocks_traces_on_wide_limbs() {
+    // The 87 bits of the largest claim
+    let layout = TraceLayout::new(87);
+    for field in FieldChoice::ALL {
+        let wide = layout.for_field(field.bits());
+        let expected = match field {
+            FieldChoice::Goldilocks => WIDE_LIMB_BITS,
+            _ => LIMB_BITS,
+        };
+        assert_eq!(wide.limb_bits(), expected, "{field:?}");
+        assert!(wide.check_field_bits(field.bits()).is_ok(), "{field:?}");
+    }
+    assert_eq!(layout.num_limbs(), 6);
+    assert_eq!(
+        layout.for_field(FieldChoice::Goldilocks.bits()).num_limbs(),
+        3
+    );
+}
+
+#[test]
+fn plonky3_rejects_unsupported_configurations() {
+    let claim = claim(BigUint::from(6u32));
+    let testing = CollatzStarkConfig::from_preset(SecurityPreset::Testing);
+    let proof = Plonky3Backend::new()
+        .with_config(testing)
+        .prove(&claim)
+        .unwrap();
+    for config in [
+        testing
+            .with_field(FieldChoice::KoalaBear)
+            .with_extension_degree(ExtensionDegree::Five),
+        testing
+            .with_field(FieldChoice::Goldilocks)
+            .with_hash(HashChoice::Poseidon2),
+        testing.with_extension_degree(ExtensionDegree::Two),
+        testing
+            .with_field(FieldChoice::Mersenne31)
+            .with_extension_degree(ExtensionDegree::Four),
+    ] {
+        assert!(config.check().is_err(), "{config:?}");
+        let backend = Plonky3Backend::new().with_config(config);
+        assert!(backend.prove(&claim).is_err(), "{config:?}");
+        assert!(backend.verify(&claim, &proof).is_err(), "{config:?}");
+    }
+}
+
+#[test]
+fn winterfell_proves_the_same_claims_with_every_field_and_hash() {
+    let preset = WinterfellPreset::Testing;
+    for claim in claims() {
+        for primitives in Primitives::all() {
+            let backend = WinterfellBackend::from_preset(preset).with_primitives(primitives);
+            let proof = backend.prove(&claim).unwrap();
+            assert_eq!(
+                proof.parameters,
+                proof_parameters(primitives, &preset.proof_options(primitives.field), false)
+            );
+            backend
+                .verify(&claim, &proof)
+                .unwrap_or_else(|error| panic!("{primitives:?}: {error}"));
+        }
+    }
+}
+
+#[test]
+fn winterfell_rejects_algebraic_hashes_over_other_fields() {
+    let claim = claim(BigUint::from(6u32));
+    for field in [WinterfellField::F62, WinterfellField::F128] {
+        for hash in [WinterfellHash::Rp64, WinterfellHash::RpJive64] {
+            let primitives = Primitives::new(field, hash);
+            let backend = WinterfellBackend::default().with_primitives(primitives);
+            assert!(backend.prove(&claim).is_err(), "{primitives:?}");
+        }
+    }
+}
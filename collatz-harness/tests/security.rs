@@ -0,0 +1,42 @@
+use collatz_core::{CollatzBackend, CollatzClaim};
+use plonky3_collatz::config::{CollatzStarkConfig, DftChoice, ExtensionDegree, HashChoice};
+use plonky3_collatz::Plonky3Backend;
+use winterfell_collatz::config::SecurityPreset;
+use winterfell_collatz::WinterfellBackend;
+
+#[test]
+fn winterfell_verifier_requires_the_minimum_security() {
+    let claim = CollatzClaim::new(27u32, 111);
+    let testing = WinterfellBackend::from_preset(SecurityPreset::Testing);
+    let proof = testing.prove(&claim).unwrap();
+    testing.verify(&claim, &proof).unwrap();
+
+    let conjectured100 = WinterfellBackend::from_preset(SecurityPreset::Conjectured100);
+    assert!(conjectured100.verify(&claim, &proof).is_err());
+    let proof = conjectured100.prove(&claim).unwrap();
+    conjectured100.verify(&claim, &proof).unwrap();
+}
+
+#[test]
+fn plonky3_proofs_verify_with_their_configuration_only() {
+    let claim = CollatzClaim::new(27u32, 111);
+    let testing = CollatzStarkConfig::from_preset(plonky3_collatz::config::SecurityPreset::Testing);
+    let configs = [
+        testing,
+        testing.with_dft(DftChoice::Radix2DitParallel),
+        testing.with_extension_degree(ExtensionDegree::Five),
+        testing.with_log_blowup(2).with_num_queries(8),
+        testing.with_hash(HashChoice::Poseidon2),
+        testing
+            .with_hash(HashChoice::Poseidon2)
+            .with_extension_degree(ExtensionDegree::Five),
+    ];
+    for (i, config) in configs.into_iter().enumerate() {
+        let backend = Plonky3Backend::new().with_config(config);
+        let proof = backend.prove(&claim).unwrap();
+        backend.verify(&claim, &proof).unwrap();
+
+        let other = Plonky3Backend::new().with_config(configs[(i + 1) % configs.len()]);
+        assert!(other.verify(&claim, &proof).is_err(), "{config:?}");
+    }
+}
@@ -0,0 +1,21 @@
+use collatz_core::BigUint;
+use collatz_harness::adversarial::ForgedTrace;
+use collatz_harness::underconstraint::{plonky3_survivors, winterfell_survivors};
+
+const STARTING_VALUES: [u32; 3] = [6, 7, 9];
+
+#[test]
+fn no_perturbation_satisfies_the_winterfell_constraints() {
+    for start in STARTING_VALUES.map(BigUint::from) {
+        let survivors = winterfell_survivors(&ForgedTrace::honest(&start)).unwrap();
+        assert!(survivors.is_empty(), "{start}: {}", survivors[0]);
+    }
+}
+
+#[test]
+fn no_perturbation_satisfies_the_plonky3_constraints() {
+    for start in STARTING_VALUES.map(BigUint::from) {
+        let survivors = plonky3_survivors(&ForgedTrace::honest(&start)).unwrap();
+        assert!(survivors.is_empty(), "{start}: {}", survivors[0]);
+    }
+}
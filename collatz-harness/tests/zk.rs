@@ -0,0 +1,96 @@
+use collatz_core::{CollatzBackend, CollatzClaim, ProofContainer};
+use plonky3_collatz::config::{CollatzStarkConfig, FieldChoice, HashChoice, SecurityPreset};
+use plonky3_collatz::Plonky3Backend;
+use winterfell_collatz::config::SecurityPreset as WinterfellPreset;
+use winterfell_collatz::WinterfellBackend;
+
+#[test]
+fn plonky3_zk_proofs_differ_across_runs_and_verify() {
+    let claim = CollatzClaim::new(27u32, 111);
+    let testing = CollatzStarkConfig::from_preset(SecurityPreset::Testing);
+    for field in [
+        FieldChoice::BabyBear,
+        FieldChoice::KoalaBear,
+        FieldChoice::Goldilocks,
+    ] {
+        let config = testing.with_field(field).with_zk(true);
+        let backend = Plonky3Backend::new().with_config(config);
+        let first = backend.prove(&claim).unwrap();
+        let second = backend.prove(&claim).unwrap();
+        assert_ne!(first.proof, second.proof, "{config:?}");
+        for proof in [&first, &second] {
+            backend
+                .verify(&claim, proof)
+                .unwrap_or_else(|error| panic!("{config:?}: {error}"));
+            // The standalone verifier picks the hiding configuration from the container
+            collatz_verifier::verify_container_with_min_security(
+                &claim,
+                proof,
+                SecurityPreset::Testing.min_conjectured_security(),
+            )
+            .unwrap_or_else(|error| panic!("{config:?}: {error}"));
+        }
+
+        // Without zero knowledge, proving is deterministic, and does not accept hiding proofs
+        let transparent = Plonky3Backend::new().with_config(config.with_zk(false));
+        let proof = transparent.prove(&claim).unwrap();
+        assert_eq!(proof.proof, transparent.prove(&claim).unwrap().proof);
+        assert!(transparent.verify(&claim, &first).is_err(), "{config:?}");
+        assert!(backend.verify(&claim, &proof).is_err(), "{config:?}");
+    }
+}
+
+#[test]
+fn plonky3_zk_requires_keccak_and_a_two_adic_field() {
+    let testing = CollatzStarkConfig::from_preset(SecurityPreset::Testing).with_zk(true);
+    assert!(testing.check().is_ok());
+    assert!(testing.with_hash(HashChoice::Poseidon2).check().is_err());
+    assert!(testing.with_field(FieldChoice::Mersenne31).check().is_err());
+}
+
+#[test]
+fn plonky3_zk_security_accounts_for_the_padded_trace() {
+    let config = CollatzStarkConfig::from_preset(SecurityPreset::Conjectured100);
+    for trace_length in [1 << 10, 1 << 20] {
+        assert_eq!(
+            config.with_zk(true).security_report(trace_length),
+            config.security_report(2 * trace_length)
+        );
+    }
+}
+
+#[test]
+fn winterfell_zk_proofs_differ_across_runs_and_verify() {
+    let claim = CollatzClaim::new(27u32, 111);
+    let backend = WinterfellBackend::from_preset(WinterfellPreset::Testing).with_zk(true);
+    let first = backend.prove(&claim).unwrap();
+    let second = backend.prove(&claim).unwrap();
+    assert_ne!(first.proof, second.proof);
+    assert_eq!(first.parameters, second.parameters);
+    for proof in [&first, &second] {
+        backend.verify(&claim, proof).unwrap();
+        collatz_verifier::verify_container_with_min_security(
+            &claim,
+            proof,
+            WinterfellPreset::Testing.min_conjectured_security(),
+        )
+        .unwrap();
+    }
+
+    // Without zero knowledge, proving is deterministic, and the public inputs bind proofs to the mode the
+    // container records
+    let transparent = WinterfellBackend::from_preset(WinterfellPreset::Testing);
+    let proof = transparent.prove(&claim).unwrap();
+    assert_eq!(proof.proof, transparent.prove(&claim).unwrap().proof);
+    assert_ne!(proof.parameters, first.parameters);
+    let relabeled = ProofContainer {
+        parameters: proof.parameters.clone(),
+        ..first.clone()
+    };
+    assert!(transparent.verify(&claim, &relabeled).is_err());
+    let relabeled = ProofContainer {
+        parameters: first.parameters.clone(),
+        ..proof
+    };
+    assert!(backend.verify(&claim, &relabeled).is_err());
+}
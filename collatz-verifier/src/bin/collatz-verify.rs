@@ -0,0 +1,37 @@
+//! Verifies a binary proof container: `collatz-verify <proof> <start> <steps>`.
+
+use std::process::ExitCode;
+
+use collatz_core::{BigUint, CollatzClaim};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [path, start, steps] = args.as_slice() else {
+        eprintln!("usage: collatz-verify <proof> <start> <steps>");
+        return ExitCode::FAILURE;
+    };
+    let (Ok(starting_value), Ok(steps_count)) = (start.parse::<BigUint>(), steps.parse::<u32>())
+    else {
+        eprintln!("error: <start> and <steps> must be non-negative integers");
+        return ExitCode::FAILURE;
+    };
+    let proof_bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(error) => {
+            eprintln!("error: cannot read {path}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let claim = CollatzClaim::new(starting_value, steps_count);
+    match collatz_verifier::verify(&claim, &proof_bytes) {
+        Ok(()) => {
+            println!("Verified that {start} reaches 1 in {steps} steps");
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
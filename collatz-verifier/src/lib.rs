@@ -0,0 +1,91 @@
+//! Verification of Collatz proofs from either backend, without trace generation or proving code.
+//!
+//! ```ignore
+//! let claim = CollatzClaim::new(27u32, 111);
+//! collatz_verifier::verify(&claim, &std::fs::read("proof.bin")?)?;
+//! ```
+
+use std::fmt;
+
+use collatz_core::{Backend, CollatzClaim, ProofContainer, ProofFormatError};
+
+/// Reasons why a proof is rejected for a claim.
+#[derive(Debug)]
+pub enum VerifyError {
+    /// The bytes are not a valid proof container.
+    Format(ProofFormatError),
+    /// The proof is for another claim than the expected one.
+    ClaimMismatch {
+        expected: CollatzClaim,
+        found: CollatzClaim,
+    },
+    Winterfell(winterfell_collatz::VerifyError),
+    Plonky3(plonky3_collatz::VerifyError),
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::Format(error) => write!(f, "{error}"),
+            VerifyError::ClaimMismatch { expected, found } => write!(
+                f,
+                "the proof is for the claim that {} reaches 1 in {} steps, expected {} in {} steps",
+                found.starting_value,
+                found.steps_count,
+                expected.starting_value,
+                expected.steps_count
+            ),
+            VerifyError::Winterfell(error) => write!(f, "winterfell: {error}"),
+            VerifyError::Plonky3(error) => write!(f, "plonky3: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Verifies that `proof_bytes`, a binary proof container from either backend, proves `claim`.
+pub fn verify(claim: &CollatzClaim, proof_bytes: &[u8]) -> Result<(), VerifyError> {
+    let container = ProofContainer::from_bytes(proof_bytes).map_err(VerifyError::Format)?;
+    verify_container(claim, &container)
+}
+
+/// Verifies that the proof in `container` proves `claim`, with the parameters recorded in the container, as
+/// long as they provide the conjectured security of the backend's default preset.
+pub fn verify_container(
+    claim: &CollatzClaim,
+    container: &ProofContainer,
+) -> Result<(), VerifyError> {
+    let min_conjectured_security = match container.backend {
+        Backend::Winterfell => {
+            winterfell_collatz::config::SecurityPreset::default().min_conjectured_security()
+        }
+        Backend::Plonky3 => {
+            plonky3_collatz::config::SecurityPreset::default().min_conjectured_security()
+        }
+    };
+    verify_container_with_min_security(claim, container, min_conjectured_security)
+}
+
+/// Verifies that the proof in `container` proves `claim`, with the field, hash and commitment scheme
+/// recorded in the container, as long as they provide at least `min_conjectured_security` bits of
+/// conjectured security.
+pub fn verify_container_with_min_security(
+    claim: &CollatzClaim,
+    container: &ProofContainer,
+    min_conjectured_security: u32,
+) -> Result<(), VerifyError> {
+    if container.claim != *claim {
+        return Err(VerifyError::ClaimMismatch {
+            expected: claim.clone(),
+            found: container.claim.clone(),
+        });
+    }
+    match container.backend {
+        Backend::Winterfell => winterfell_collatz::verify(container, min_conjectured_security)
+            .map_err(VerifyError::Winterfell),
+        Backend::Plonky3 => {
+            plonky3_collatz::verify_min_security(container, min_conjectured_security)
+                .map_err(VerifyError::Plonky3)
+        }
+    }
+}
@@ -1,48 +1,136 @@
-use p3_air::{Air, AirBuilder, BaseAir};
+use collatz_core::{BigUint, CollatzError, TraceLayout, CARRY_BITS, CARRY_OFFSET};
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, BaseAirWithPublicValues};
 use p3_field::Field;
 use p3_field::PrimeCharacteristicRing;
 use p3_matrix::Matrix;
 
 /// AIR for proving Collatz conjecture sequences.
-/// The trace consists of N columns, each representing a bit in the binary representation (LSB first)
-/// of the current number in the sequence, plus two additional columns:
+/// The trace columns are described by [`TraceLayout`]: N columns, each representing a bit in the binary
+/// representation (LSB first) of the current number in the sequence, plus additional columns:
 /// - Column N: step counter
 /// - Column N+1: transition flag (1 = transition, 0 = repeat)
-pub struct CollatzAir<const N: usize> {
-    pub starting_value: u32,
-    pub steps_count: u32,
+/// - Column N+2: done flag (1 iff the current number is 1), which forbids any further transition
+/// - Column N+3: inverse witness for the done flag
+/// - Columns N+4..: carry bits between the limbs of the current number
+///
+/// N is chosen at runtime through `layout`, and the limbs are as wide as the field allows (see
+/// [`TraceLayout::for_field`]); the verifier must use the same layout as the prover, otherwise the opened
+/// trace width does not match and verification fails.
+///
+/// The claim itself is not part of the AIR: the N bits of the starting value, followed by the step count,
+/// are the public values (see [`CollatzAir::public_values`]), which the challenger observes before
+/// sampling any challenge, so that a proof is bound to the claim it was produced for.
+pub struct CollatzAir {
+    layout: TraceLayout,
 }
 
-impl<const N: usize, F: Field> BaseAir<F> for CollatzAir<N> {
+impl CollatzAir {
+    /// Creates the AIR for traces with the given `layout`, to be proven over the field `F`.
+    ///
+    /// Fails if `F` is too small for the limb-wise Collatz rule of `layout`.
+    pub fn new<F: Field>(layout: TraceLayout) -> Result<Self, CollatzError> {
+        layout.check_field_bits(F::order().bits() as u32)?;
+        Ok(Self { layout })
+    }
+
+    /// The column layout of the traces this AIR constrains.
+    pub fn layout(&self) -> TraceLayout {
+        self.layout
+    }
+
+    /// Public values for the claim that `starting_value` reaches 1 in `steps_count` steps.
+    ///
+    /// Fails if the starting value does not fit in the bit columns.
+    pub fn public_values<F: Field>(
+        &self,
+        starting_value: &BigUint,
+        steps_count: u32,
+    ) -> Result<Vec<F>, CollatzError> {
+        self.layout.check_fits(starting_value)?;
+        Ok(self
+            .layout
+            .value_bits(starting_value)
+            .map(F::from_bool)
+            .chain([F::from_u32(steps_count)])
+            .collect())
+    }
+
+    /// Weighted sum of the bits of limb `k` in `row`.
+    fn limb<AB: AirBuilder>(&self, row: &[AB::Var], k: usize) -> AB::Expr {
+        self.layout.limb(k).rev().fold(AB::Expr::ZERO, |acc, i| {
+            acc * AB::Expr::TWO + row[self.layout.bit(i)]
+        })
+    }
+
+    /// `(limb_0 - 1) + 2 * (limb_1 + ... + limb_{L-1})` in `row`, which is zero iff the row's value is 1.
+    fn done_sum<AB: AirBuilder>(&self, row: &[AB::Var]) -> AB::Expr {
+        (1..self.layout.num_limbs()).fold(self.limb::<AB>(row, 0) - AB::Expr::ONE, |acc, k| {
+            acc + AB::Expr::TWO * self.limb::<AB>(row, k)
+        })
+    }
+
+    /// Carry into limb `k` encoded in `row`.
+    /// There is no carry into the first limb, nor out of the last one.
+    fn carry<AB: AirBuilder>(&self, row: &[AB::Var], k: usize) -> AB::Expr {
+        if k == 0 || k == self.layout.num_limbs() {
+            return AB::Expr::ZERO;
+        }
+        (0..CARRY_BITS).rev().fold(AB::Expr::ZERO, |acc, j| {
+            acc * AB::Expr::TWO + row[self.layout.carry_bit(k, j)]
+        }) - AB::Expr::from_u64(CARRY_OFFSET)
+    }
+}
+
+impl<F: Field> BaseAir<F> for CollatzAir {
     fn width(&self) -> usize {
-        N + 2
+        self.layout.width()
+    }
+}
+
+impl<F: Field> BaseAirWithPublicValues<F> for CollatzAir {
+    fn num_public_values(&self) -> usize {
+        // The bits of the starting value, and the step count
+        self.layout.num_bits() + 1
     }
 }
 
-impl<AB: AirBuilder, const N: usize> Air<AB> for CollatzAir<N> {
+impl<AB: AirBuilderWithPublicValues> Air<AB> for CollatzAir {
     fn eval(&self, builder: &mut AB) {
+        let layout = self.layout;
+        let n = layout.num_bits();
+        let public_values = builder.public_values().to_vec();
         let main = builder.main();
-        let local = main.row_slice(0).expect("The matrix is empty?");
-        let next = main.row_slice(1).expect("The matrix only has 1 row?");
-
-        let value_bits = &local[0..N];
-        let next_value_bits = &next[0..N];
-        let step_counter = local[N];
-        let next_step_counter = next[N];
-        let is_transition = local[N + 1];
-        let next_is_transition = next[N + 1];
+        let (Some(local), Some(next), Some(starting_value_bits), Some(&steps_count)) = (
+            main.row_slice(0),
+            main.row_slice(1),
+            public_values.get(..n),
+            public_values.get(n),
+        ) else {
+            // Plonky3's builders always provide a window of two rows and `num_public_values` public values:
+            // anything else is rejected rather than evaluated
+            builder.assert_zero(AB::Expr::ONE);
+            return;
+        };
+
+        let value_bits: Vec<_> = (0..n).map(|i| local[layout.bit(i)]).collect();
+        let next_value_bits: Vec<_> = (0..n).map(|i| next[layout.bit(i)]).collect();
+        let step_counter = local[layout.step_counter()];
+        let next_step_counter = next[layout.step_counter()];
+        let is_transition = local[layout.transition_flag()];
+        let next_is_transition = next[layout.transition_flag()];
+        let is_done = local[layout.done_flag()];
+        let done_inverse = local[layout.done_inverse()];
 
         // ------------------------------------------------------------------------------------------------
         // Initial boundary constraints
         // ------------------------------------------------------------------------------------------------
 
         // Enforce starting values based on the binary representation of `starting_value`
-        // `starting_value` is part of the public input.
-        for i in 0..N {
-            builder.when_first_row().assert_eq(
-                value_bits[i],
-                AB::Expr::from_bool((self.starting_value >> i & 1) == 1),
-            );
+        // `starting_value` is part of the public values.
+        for i in 0..n {
+            builder
+                .when_first_row()
+                .assert_eq(value_bits[i], starting_value_bits[i]);
         }
 
         // Initial step counter is 0
@@ -53,6 +141,10 @@ impl<AB: AirBuilder, const N: usize> Air<AB> for CollatzAir<N> {
         builder
             .when_first_row()
             .assert_eq(is_transition, AB::Expr::ZERO);
+        // ... hence it has no carries
+        for column in layout.carry_columns() {
+            builder.when_first_row().assert_zero(local[column]);
+        }
 
         // ------------------------------------------------------------------------------------------------
         // Transition constraints
@@ -61,7 +153,7 @@ impl<AB: AirBuilder, const N: usize> Air<AB> for CollatzAir<N> {
         // Consistency constraint: ensure each cell in the binary decomposition column is indeed a bit.
         // Note that here we constrain the next row's value bits
         // (the first row is already guaranteed to be binary and correct due to the boundary constraint check)
-        for i in 0..N {
+        for i in 0..n {
             builder.when_transition().assert_bool(next_value_bits[i]);
         }
         // Consistency constraint: ensure the next is_transition value is indeed a bit.
@@ -70,30 +162,42 @@ impl<AB: AirBuilder, const N: usize> Air<AB> for CollatzAir<N> {
         builder
             .when_transition()
             .assert_bool(next_is_transition.clone());
-
-        let current_weighted_sum = (0..N).fold(AB::Expr::ZERO, |acc, i| {
-            acc + (AB::Expr::from_u32(2u32.pow(i as u32)) * value_bits[i])
-        });
-
-        let next_weighted_sum = (0..N).fold(AB::Expr::ZERO, |acc, i| {
-            acc + (AB::Expr::from_u32(2u32.pow(i as u32)) * next_value_bits[i])
-        });
+        // Consistency constraint: ensure the next carry bits are indeed bits, and zero if the next row is not a transition.
+        for column in layout.carry_columns() {
+            builder
+                .when_transition()
+                .assert_zero(next[column] * (next[column] - next_is_transition));
+        }
 
         let is_odd = value_bits[0].clone();
-
-        // Main transition constraint: apply the collatz_rule OR repeat row
-        builder.when_transition().assert_eq(
-            // Apply the Collatz transition rule
-            next_is_transition.clone()
-                * ((AB::Expr::TWO * next_weighted_sum.clone())
-                    - (is_odd
-                        * AB::Expr::TWO
-                        * (current_weighted_sum.clone() * AB::Expr::from_u32(3) + AB::Expr::ONE)
-                        + (AB::Expr::ONE - is_odd) * current_weighted_sum.clone())),
-            // No transition, repeat the current row
-            (AB::Expr::ONE - next_is_transition.clone())
-                * (current_weighted_sum - next_weighted_sum),
-        );
+        let two_pow_limb = AB::Expr::from_u64(1 << layout.limb_bits());
+
+        // Main transition constraint: apply the collatz_rule OR repeat row, limb by limb.
+        // The weighted sum of the whole value would wrap around the field for large N, so instead we check
+        // 2 * next_k + 2^limb_bits * carry_{k+1} = is_odd * 2 * (current_k * 3 + [k == 0]) + (1 - is_odd) * current_k + carry_k
+        for k in 0..layout.num_limbs() {
+            let current_limb = self.limb::<AB>(&local, k);
+            let next_limb = self.limb::<AB>(&next, k);
+            let increment = if k == 0 {
+                AB::Expr::ONE
+            } else {
+                AB::Expr::ZERO
+            };
+
+            builder.when_transition().assert_eq(
+                // Apply the Collatz transition rule
+                next_is_transition.clone()
+                    * ((AB::Expr::TWO * next_limb.clone()
+                        + two_pow_limb.clone() * self.carry::<AB>(&next, k + 1))
+                        - (is_odd
+                            * AB::Expr::TWO
+                            * (current_limb.clone() * AB::Expr::from_u32(3) + increment)
+                            + (AB::Expr::ONE - is_odd) * current_limb.clone()
+                            + self.carry::<AB>(&next, k))),
+                // No transition, repeat the current row
+                (AB::Expr::ONE - next_is_transition.clone()) * (current_limb - next_limb),
+            );
+        }
 
         // Step counter constraint:
         // If next_is_transition = 1, increment step counter
@@ -106,6 +210,25 @@ impl<AB: AirBuilder, const N: usize> Air<AB> for CollatzAir<N> {
             (AB::Expr::ONE - next_is_transition) * (step_counter - next_step_counter),
         );
 
+        // ------------------------------------------------------------------------------------------------
+        // Done flag constraints, on every row
+        // ------------------------------------------------------------------------------------------------
+
+        // The done sum is zero iff the current value is 1, so:
+        // - done = 1 forces the done sum to zero,
+        // - done = 0 forces the done sum to have an inverse, i.e. to be non-zero.
+        let done_sum = self.done_sum::<AB>(&local);
+        builder.assert_bool(is_done);
+        builder.assert_zero(is_done * done_sum.clone());
+        builder.assert_zero((AB::Expr::ONE - is_done) * (done_sum * done_inverse - AB::Expr::ONE));
+        // The inverse is zero when done, so that the trace is unique
+        builder.assert_zero(is_done * done_inverse);
+        // Once done, the next row must not be a transition: the sequence stops at the first 1,
+        // so the final step counter is the number of steps to first reach 1
+        builder
+            .when_transition()
+            .assert_zero(is_done * next_is_transition);
+
         // ------------------------------------------------------------------------------------------------
         // Ending boundary constraints
         // ------------------------------------------------------------------------------------------------
@@ -114,16 +237,14 @@ impl<AB: AirBuilder, const N: usize> Air<AB> for CollatzAir<N> {
         builder
             .when_last_row()
             .assert_eq(value_bits[0], AB::Expr::ONE);
-        for i in 1..N {
+        for i in 1..n {
             builder
                 .when_last_row()
                 .assert_eq(value_bits[i], AB::Expr::ZERO);
         }
 
         // The last row's step counter should match the expected steps_count
-        builder
-            .when_last_row()
-            .assert_eq(step_counter, AB::Expr::from_u32(self.steps_count));
+        builder.when_last_row().assert_eq(step_counter, steps_count);
         // We don't have an explicit ending boundary constraint for the last row's is_transition flag:
         // if the trace_length perfectly matches the steps_count without padding, then it's a transition row, otherwise it's not.
     }
@@ -0,0 +1,61 @@
+use collatz_core::{
+    Backend, CollatzBackend, CollatzClaim, CollatzError, CollatzWitness, ProofContainer,
+    SequenceLimits,
+};
+
+use crate::config::CollatzStarkConfig;
+use crate::prover::prove_witness;
+use crate::verifier::verify;
+
+/// [`CollatzBackend`] proving with Plonky3, with a fixed STARK configuration.
+#[derive(Clone, Debug, Default)]
+pub struct Plonky3Backend {
+    config: CollatzStarkConfig,
+    limits: SequenceLimits,
+}
+
+impl Plonky3Backend {
+    /// Proves and verifies with the default [`CollatzStarkConfig`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(mut self, config: CollatzStarkConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Bounds the sequences this backend accepts to prove.
+    pub fn with_limits(mut self, limits: SequenceLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+}
+
+impl CollatzBackend for Plonky3Backend {
+    type Proof = ProofContainer;
+
+    fn backend(&self) -> Backend {
+        Backend::Plonky3
+    }
+
+    fn prove(&self, claim: &CollatzClaim) -> Result<Self::Proof, CollatzError> {
+        let witness = CollatzWitness::new(claim.starting_value.clone(), &self.limits)?;
+        if witness.steps() != claim.steps_count {
+            return Err(CollatzError::FalseClaim {
+                claim: claim.clone(),
+                steps: witness.steps(),
+            });
+        }
+        prove_witness(witness, &self.config)
+    }
+
+    fn verify(&self, claim: &CollatzClaim, proof: &Self::Proof) -> Result<(), CollatzError> {
+        // Check the proof against the given claim, whatever the container says
+        let container = ProofContainer {
+            claim: claim.clone(),
+            ..proof.clone()
+        };
+        Ok(verify(&container, &self.config)?)
+    }
+}
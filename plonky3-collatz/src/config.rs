@@ -0,0 +1,916 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use collatz_core::{FriParameters, ProofParameters, SecurityReport};
+use p3_baby_bear::BabyBear;
+use p3_challenger::{
+    DuplexChallenger, HashChallenger, SerializingChallenger32, SerializingChallenger64,
+};
+use p3_circle::CirclePcs;
+use p3_commit::ExtensionMmcs;
+use p3_dft::TwoAdicSubgroupDft;
+use p3_field::extension::{
+    BinomialExtensionField, BinomiallyExtendable, ComplexExtendable, HasTwoAdicBinomialExtension,
+};
+use p3_field::{Field, PrimeField64, TwoAdicField};
+use p3_fri::{FriConfig, HidingFriPcs, TwoAdicFriPcs};
+use p3_goldilocks::Goldilocks;
+use p3_keccak::Keccak256Hash;
+use p3_koala_bear::KoalaBear;
+use p3_merkle_tree::{MerkleTreeHidingMmcs, MerkleTreeMmcs};
+use p3_mersenne_31::Mersenne31;
+use p3_symmetric::{
+    CompressionFunctionFromHasher, CryptographicPermutation, PaddingFreeSponge, SerializingHasher,
+    TruncatedPermutation,
+};
+use p3_uni_stark::StarkConfig;
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+
+pub use p3_dft::{Radix2Bowers, Radix2DitParallel};
+
+// Type definitions, over the base field `F`
+/// The default base field.
+pub type Val = BabyBear;
+/// The degree `D` extension of `F` the challenges are drawn from.
+pub type Challenge<F, const D: usize> = BinomialExtensionField<F, D>;
+pub type ByteHash = Keccak256Hash;
+pub type FieldHash = SerializingHasher<ByteHash>;
+pub type MyCompress = CompressionFunctionFromHasher<ByteHash, 2, 32>;
+pub type ValMmcs<F> = MerkleTreeMmcs<F, u8, FieldHash, MyCompress, 32>;
+pub type ChallengeMmcs<F, const D: usize> = ExtensionMmcs<F, Challenge<F, D>, ValMmcs<F>>;
+/// The Keccak256 challenger over fields of up to 32 bits.
+pub type Challenger32<F> = SerializingChallenger32<F, HashChallenger<u8, ByteHash, 32>>;
+/// The Keccak256 challenger over fields of up to 64 bits.
+pub type Challenger64<F> = SerializingChallenger64<F, HashChallenger<u8, ByteHash, 32>>;
+pub type Pcs<F, Dft, const D: usize> = TwoAdicFriPcs<F, Dft, ValMmcs<F>, ChallengeMmcs<F, D>>;
+pub type KeccakConfig<F, Dft, const D: usize, Challenger> =
+    StarkConfig<Pcs<F, Dft, D>, Challenge<F, D>, Challenger>;
+// The same with hiding commitments, for zero knowledge: the Merkle leaves are salted with `SALT_ELEMS` random
+// elements, and the PCS pads the trace with random rows and adds random codewords to the FRI batch
+pub const SALT_ELEMS: usize = 4;
+pub const NUM_RANDOM_CODEWORDS: usize = 4;
+pub type ZkRng = SmallRng;
+pub type HidingValMmcs<F> =
+    MerkleTreeHidingMmcs<F, u8, FieldHash, MyCompress, ZkRng, 32, SALT_ELEMS>;
+pub type HidingChallengeMmcs<F, const D: usize> =
+    ExtensionMmcs<F, Challenge<F, D>, HidingValMmcs<F>>;
+pub type HidingPcs<F, Dft, const D: usize> =
+    HidingFriPcs<F, Dft, HidingValMmcs<F>, HidingChallengeMmcs<F, D>, ZkRng>;
+pub type HidingKeccakConfig<F, Dft, const D: usize, Challenger> =
+    StarkConfig<HidingPcs<F, Dft, D>, Challenge<F, D>, Challenger>;
+/// The Circle STARK PCS over `F`, which need not be two-adic but must have a complex extension.
+pub type CircleKeccakPcs<F, const D: usize> = CirclePcs<F, ValMmcs<F>, ChallengeMmcs<F, D>>;
+pub type CircleKeccakConfig<F, const D: usize, Challenger> =
+    StarkConfig<CircleKeccakPcs<F, D>, Challenge<F, D>, Challenger>;
+
+// The same with a width 16 Poseidon2 permutation `Perm` over `F` for the Merkle trees and the challenger,
+// which is cheaper to verify in an algebraic circuit
+pub type Poseidon2Hash<Perm> = PaddingFreeSponge<Perm, 16, 8, 8>;
+pub type Poseidon2Compress<Perm> = TruncatedPermutation<Perm, 2, 8, 16>;
+pub type Poseidon2ValMmcs<F, Perm> = MerkleTreeMmcs<
+    <F as Field>::Packing,
+    <F as Field>::Packing,
+    Poseidon2Hash<Perm>,
+    Poseidon2Compress<Perm>,
+    8,
+>;
+pub type Poseidon2ChallengeMmcs<F, Perm, const D: usize> =
+    ExtensionMmcs<F, Challenge<F, D>, Poseidon2ValMmcs<F, Perm>>;
+pub type Poseidon2Challenger<F, Perm> = DuplexChallenger<F, Perm, 16, 8>;
+pub type Poseidon2Pcs<F, Perm, Dft, const D: usize> =
+    TwoAdicFriPcs<F, Dft, Poseidon2ValMmcs<F, Perm>, Poseidon2ChallengeMmcs<F, Perm, D>>;
+pub type Poseidon2Config<F, Perm, Dft, const D: usize> =
+    StarkConfig<Poseidon2Pcs<F, Perm, Dft, D>, Challenge<F, D>, Poseidon2Challenger<F, Perm>>;
+
+/// The base field of the trace.
+///
+/// The trace layout does not depend on the field: every field proves the same traces, but larger fields
+/// need smaller extensions for the same security.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FieldChoice {
+    #[default]
+    BabyBear,
+    KoalaBear,
+    Goldilocks,
+    /// Not two-adic: proven with the Circle STARK PCS, over the points of the circle `x^2 + y^2 = 1`.
+    Mersenne31,
+}
+
+impl FieldChoice {
+    pub const ALL: [FieldChoice; 4] = [
+        FieldChoice::BabyBear,
+        FieldChoice::KoalaBear,
+        FieldChoice::Goldilocks,
+        FieldChoice::Mersenne31,
+    ];
+
+    /// Number of bits of the order of the field.
+    pub fn bits(&self) -> u32 {
+        let order = match self {
+            FieldChoice::BabyBear => BabyBear::ORDER_U64,
+            FieldChoice::KoalaBear => KoalaBear::ORDER_U64,
+            FieldChoice::Goldilocks => Goldilocks::ORDER_U64,
+            FieldChoice::Mersenne31 => Mersenne31::ORDER_U64,
+        };
+        u64::BITS - order.leading_zeros()
+    }
+
+    /// Degrees of the extensions of the field the challenges can be drawn from, smallest first.
+    pub fn extension_degrees(&self) -> &'static [ExtensionDegree] {
+        match self {
+            FieldChoice::BabyBear => &[ExtensionDegree::Four, ExtensionDegree::Five],
+            FieldChoice::KoalaBear => &[ExtensionDegree::Four],
+            FieldChoice::Goldilocks => &[ExtensionDegree::Two],
+            FieldChoice::Mersenne31 => &[ExtensionDegree::Three],
+        }
+    }
+
+    /// Whether the field is proven with the Circle STARK PCS rather than the two-adic FRI PCS.
+    pub fn is_circle(&self) -> bool {
+        *self == FieldChoice::Mersenne31
+    }
+
+    /// Hashes which can be used over the field.
+    pub fn hashes(&self) -> &'static [HashChoice] {
+        match self {
+            FieldChoice::BabyBear | FieldChoice::KoalaBear => {
+                &[HashChoice::Keccak256, HashChoice::Poseidon2]
+            }
+            // Plonky3 has no width 16 Poseidon2 permutation with fixed constants over these fields
+            FieldChoice::Goldilocks | FieldChoice::Mersenne31 => &[HashChoice::Keccak256],
+        }
+    }
+}
+
+/// Bounds of the FRI parameters of a [`CollatzStarkConfig`]. Beyond them, proofs would take too long to
+/// check, or their domains would not fit in the two-adic subgroups of the fields.
+pub const MAX_LOG_BLOWUP: usize = 8;
+pub const MAX_NUM_QUERIES: usize = 255;
+pub const MAX_PROOF_OF_WORK_BITS: usize = 30;
+pub const MAX_LOG_FINAL_POLY_LEN: usize = 16;
+
+/// A [`CollatzStarkConfig`] combining choices which cannot be used together.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnsupportedConfig {
+    ExtensionDegree(FieldChoice, ExtensionDegree),
+    Hash(FieldChoice, HashChoice),
+    /// Zero knowledge is only supported with Keccak256 and the two-adic FRI PCS.
+    ZeroKnowledge(FieldChoice, HashChoice),
+    /// A STARK configuration over an extension of another degree than the configuration's was requested.
+    WrongExtensionDegree(ExtensionDegree, usize),
+    /// A FRI parameter, by name, is outside of its bounds.
+    FriParameter {
+        name: &'static str,
+        value: usize,
+        min: usize,
+        max: usize,
+    },
+}
+
+impl fmt::Display for UnsupportedConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnsupportedConfig::ExtensionDegree(field, degree) => write!(
+                f,
+                "{field:?} has no supported extension of degree {}",
+                degree.degree()
+            ),
+            UnsupportedConfig::Hash(field, hash) => {
+                write!(f, "{hash:?} is not supported over {field:?}")
+            }
+            UnsupportedConfig::ZeroKnowledge(field, hash) => {
+                write!(
+                    f,
+                    "zero knowledge is not supported with {hash:?} over {field:?}"
+                )
+            }
+            UnsupportedConfig::WrongExtensionDegree(degree, requested) => write!(
+                f,
+                "the configuration has an extension of degree {}, not {requested}",
+                degree.degree()
+            ),
+            UnsupportedConfig::FriParameter {
+                name,
+                value,
+                min,
+                max,
+            } => write!(f, "{name} must be between {min} and {max}, not {value}"),
+        }
+    }
+}
+
+impl std::error::Error for UnsupportedConfig {}
+
+/// The hash of the Merkle trees and of the challenger.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashChoice {
+    Keccak256,
+    /// Poseidon2 over the base field, with a width of 16 and digests of 8 elements.
+    Poseidon2,
+}
+
+impl HashChoice {
+    pub const ALL: [HashChoice; 2] = [HashChoice::Keccak256, HashChoice::Poseidon2];
+
+    /// Collision resistance of the hash over `field`, in bits.
+    pub fn collision_resistance(&self, field: FieldChoice) -> u32 {
+        match self {
+            HashChoice::Keccak256 => 128,
+            // Half of the 8 digest elements
+            HashChoice::Poseidon2 => 4 * field.bits(),
+        }
+    }
+}
+
+/// Degree of the extension of the base field the challenges are drawn from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExtensionDegree {
+    Two,
+    Three,
+    Four,
+    Five,
+}
+
+impl ExtensionDegree {
+    pub fn degree(&self) -> usize {
+        match self {
+            ExtensionDegree::Two => 2,
+            ExtensionDegree::Three => 3,
+            ExtensionDegree::Four => 4,
+            ExtensionDegree::Five => 5,
+        }
+    }
+}
+
+/// The DFT used to compute the low-degree extensions over two-adic fields. The Circle STARK PCS uses its
+/// own circle FFT.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DftChoice {
+    Radix2Bowers,
+    Radix2DitParallel,
+}
+
+impl DftChoice {
+    pub const ALL: [DftChoice; 2] = [DftChoice::Radix2Bowers, DftChoice::Radix2DitParallel];
+}
+
+/// Named configurations, by the security they provide (see [`CollatzStarkConfig::security_report`]).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SecurityPreset {
+    /// Few queries for fast tests: about 15 bits of conjectured security.
+    Testing,
+    /// 100 bits of conjectured security, with 16 bits of proof of work.
+    #[default]
+    Conjectured100,
+    /// 128 bits of conjectured security, over the degree 5 extension of BabyBear.
+    Conjectured128,
+}
+
+impl SecurityPreset {
+    pub const ALL: [SecurityPreset; 3] = [
+        SecurityPreset::Testing,
+        SecurityPreset::Conjectured100,
+        SecurityPreset::Conjectured128,
+    ];
+
+    /// Conjectured security of proofs produced with this preset, for traces of up to `2^20` rows.
+    pub fn min_conjectured_security(&self) -> u32 {
+        match self {
+            SecurityPreset::Testing => 15,
+            SecurityPreset::Conjectured100 => 100,
+            SecurityPreset::Conjectured128 => 128,
+        }
+    }
+}
+
+/// Builder for the STARK configuration the prover and the verifier must agree on.
+///
+/// The field, the extension degree and the DFT are types in Plonky3: the prover and the verifier
+/// instantiate the configuration they select with `with_stark_config!`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CollatzStarkConfig {
+    log_blowup: usize,
+    num_queries: usize,
+    proof_of_work_bits: usize,
+    log_final_poly_len: usize,
+    field: FieldChoice,
+    extension_degree: ExtensionDegree,
+    dft: DftChoice,
+    hash: HashChoice,
+    zk: bool,
+}
+
+impl CollatzStarkConfig {
+    /// The preset over BabyBear.
+    pub fn from_preset(preset: SecurityPreset) -> Self {
+        // (log blowup, queries, proof of work bits, extension degree)
+        let (log_blowup, num_queries, proof_of_work_bits, extension_degree) = match preset {
+            SecurityPreset::Testing => (1, 16, 0, ExtensionDegree::Four),
+            // 1 bit per query, plus the proof of work
+            SecurityPreset::Conjectured100 => (1, 85, 16, ExtensionDegree::Four),
+            // The degree 4 extension only has 124 bits
+            SecurityPreset::Conjectured128 => (2, 57, 16, ExtensionDegree::Five),
+        };
+        Self {
+            log_blowup,
+            num_queries,
+            proof_of_work_bits,
+            log_final_poly_len: 0,
+            field: FieldChoice::BabyBear,
+            extension_degree,
+            dft: DftChoice::Radix2Bowers,
+            hash: HashChoice::Keccak256,
+            zk: false,
+        }
+    }
+
+    /// The configuration whose [`CollatzStarkConfig::proof_parameters`] are `parameters`, e.g. to verify a
+    /// proof with the configuration recorded in its container, or `None` if no supported configuration has
+    /// these parameters.
+    ///
+    /// The configuration is only as secure as its parameters: verifiers should check its
+    /// [`CollatzStarkConfig::security_report`] before accepting a proof.
+    pub fn from_parameters(parameters: &ProofParameters) -> Option<Self> {
+        let fri = &parameters.fri;
+        let base = Self {
+            log_blowup: fri.log_blowup as usize,
+            num_queries: fri.num_queries as usize,
+            proof_of_work_bits: fri.proof_of_work_bits as usize,
+            log_final_poly_len: fri.log_final_poly_len as usize,
+            ..Self::default()
+        };
+        // Out of bounds parameters are rejected by every configuration
+        base.check().ok()?;
+
+        // The choices are recorded by name: find the supported configuration with the same parameters
+        FieldChoice::ALL
+            .into_iter()
+            .flat_map(|field| {
+                let degrees = field.extension_degrees().iter();
+                degrees.map(move |&degree| base.with_field(field).with_extension_degree(degree))
+            })
+            .flat_map(|config| HashChoice::ALL.map(|hash| config.with_hash(hash)))
+            .flat_map(|config| DftChoice::ALL.map(|dft| config.with_dft(dft)))
+            .flat_map(|config| [false, true].map(|zk| config.with_zk(zk)))
+            .find(|config| config.check().is_ok() && config.proof_parameters() == *parameters)
+    }
+
+    /// Sets the log of the blowup factor, which must be at least 1 as the constraints have degree 3.
+    pub fn with_log_blowup(mut self, log_blowup: usize) -> Self {
+        self.log_blowup = log_blowup;
+        self
+    }
+
+    pub fn with_num_queries(mut self, num_queries: usize) -> Self {
+        self.num_queries = num_queries;
+        self
+    }
+
+    pub fn with_proof_of_work_bits(mut self, proof_of_work_bits: usize) -> Self {
+        self.proof_of_work_bits = proof_of_work_bits;
+        self
+    }
+
+    /// Sets the base field, along with its largest supported extension.
+    pub fn with_field(mut self, field: FieldChoice) -> Self {
+        self.field = field;
+        self.extension_degree = *field
+            .extension_degrees()
+            .last()
+            .expect("Every field has an extension");
+        self
+    }
+
+    pub fn with_extension_degree(mut self, extension_degree: ExtensionDegree) -> Self {
+        self.extension_degree = extension_degree;
+        self
+    }
+
+    pub fn with_dft(mut self, dft: DftChoice) -> Self {
+        self.dft = dft;
+        self
+    }
+
+    pub fn with_hash(mut self, hash: HashChoice) -> Self {
+        self.hash = hash;
+        self
+    }
+
+    /// Makes proofs zero knowledge, so that they reveal nothing about the sequence but the claim. Their
+    /// commitments are randomized: two proofs of the same claim differ.
+    pub fn with_zk(mut self, zk: bool) -> Self {
+        self.zk = zk;
+        self
+    }
+
+    pub fn field(&self) -> FieldChoice {
+        self.field
+    }
+
+    pub fn extension_degree(&self) -> ExtensionDegree {
+        self.extension_degree
+    }
+
+    pub fn dft(&self) -> DftChoice {
+        self.dft
+    }
+
+    pub fn hash(&self) -> HashChoice {
+        self.hash
+    }
+
+    pub fn zk(&self) -> bool {
+        self.zk
+    }
+
+    /// Checks that the field supports the extension degree and the hash, and that the FRI parameters are
+    /// within their bounds.
+    pub fn check(&self) -> Result<(), UnsupportedConfig> {
+        for (name, value, min, max) in [
+            ("log blowup", self.log_blowup, 1, MAX_LOG_BLOWUP),
+            ("queries", self.num_queries, 1, MAX_NUM_QUERIES),
+            (
+                "pow bits",
+                self.proof_of_work_bits,
+                0,
+                MAX_PROOF_OF_WORK_BITS,
+            ),
+            (
+                "log final poly len",
+                self.log_final_poly_len,
+                0,
+                MAX_LOG_FINAL_POLY_LEN,
+            ),
+        ] {
+            if !(min..=max).contains(&value) {
+                return Err(UnsupportedConfig::FriParameter {
+                    name,
+                    value,
+                    min,
+                    max,
+                });
+            }
+        }
+        if !self
+            .field
+            .extension_degrees()
+            .contains(&self.extension_degree)
+        {
+            return Err(UnsupportedConfig::ExtensionDegree(
+                self.field,
+                self.extension_degree,
+            ));
+        }
+        if !self.field.hashes().contains(&self.hash) {
+            return Err(UnsupportedConfig::Hash(self.field, self.hash));
+        }
+        if self.zk && (self.hash != HashChoice::Keccak256 || self.field.is_circle()) {
+            return Err(UnsupportedConfig::ZeroKnowledge(self.field, self.hash));
+        }
+        Ok(())
+    }
+
+    /// Minimum number of rows of traces proven with this configuration.
+    ///
+    /// Windows span two rows, and FRI folds each committed polynomial down to its final polynomial, so the
+    /// shortest one, a trace-height quotient chunk, must have more rows than the final polynomial has
+    /// coefficients. The Circle STARK PCS first folds the circle domain onto a line, which halves it once
+    /// more.
+    pub fn min_trace_length(&self) -> usize {
+        if self.field.is_circle() {
+            4 << self.log_final_poly_len
+        } else {
+            2 << self.log_final_poly_len
+        }
+    }
+
+    /// Conjectured and proven security of proofs of traces of `trace_length` rows.
+    pub fn security_report(&self, trace_length: usize) -> SecurityReport {
+        // The hiding PCS pads the trace with as many random rows as it has, doubling the evaluation domain
+        let committed_length = if self.zk {
+            2 * trace_length
+        } else {
+            trace_length
+        };
+        SecurityReport::estimate(
+            self.field.bits() * self.extension_degree.degree() as u32,
+            committed_length.ilog2() + self.log_blowup as u32,
+            self.log_blowup as u32,
+            self.num_queries as u32,
+            self.proof_of_work_bits as u32,
+            self.hash.collision_resistance(self.field),
+        )
+    }
+
+    /// Parameters recorded in the proof container for proofs produced with this configuration.
+    pub fn proof_parameters(&self) -> ProofParameters {
+        ProofParameters {
+            field: format!(
+                "{:?}, degree {} binomial extension",
+                self.field,
+                self.extension_degree.degree()
+            ),
+            hash: format!("{:?}", self.hash),
+            pcs: if self.field.is_circle() {
+                "CircleFri".to_string()
+            } else {
+                format!(
+                    "{}TwoAdicFri({:?})",
+                    if self.zk { "Hiding" } else { "" },
+                    self.dft
+                )
+            },
+            fri: FriParameters {
+                log_blowup: self.log_blowup as u32,
+                num_queries: self.num_queries as u32,
+                proof_of_work_bits: self.proof_of_work_bits as u32,
+                log_final_poly_len: self.log_final_poly_len as u32,
+            },
+        }
+    }
+
+    /// The STARK configuration hashing with Keccak256, over the degree `D` extension of `F`, with `Dft`
+    /// computing the low-degree extensions and `challenger` the Keccak256 challenger for `F`. Fails if `D`
+    /// is not the extension degree of this configuration.
+    pub fn keccak_config<F, Dft, const D: usize, Challenger>(
+        &self,
+        challenger: Challenger,
+    ) -> Result<KeccakConfig<F, Dft, D, Challenger>, UnsupportedConfig>
+    where
+        F: TwoAdicField + BinomiallyExtendable<D> + HasTwoAdicBinomialExtension<D>,
+        Dft: TwoAdicSubgroupDft<F> + Default,
+    {
+        let byte_hash = ByteHash {};
+        let val_mmcs = ValMmcs::new(FieldHash::new(byte_hash), MyCompress::new(byte_hash));
+        let pcs = Pcs::new(
+            Dft::default(),
+            val_mmcs.clone(),
+            self.fri_config::<_, D>(ChallengeMmcs::new(val_mmcs))?,
+        );
+        Ok(KeccakConfig::new(pcs, challenger))
+    }
+
+    /// The zero-knowledge STARK configuration hashing with Keccak256, over the degree `D` extension of `F`,
+    /// with `Dft` computing the low-degree extensions and `challenger` the Keccak256 challenger for `F`.
+    /// The randomness of the commitments is drawn from the OS, with the `prover` feature. Fails if `D` is
+    /// not the extension degree of this configuration.
+    pub fn hiding_keccak_config<F, Dft, const D: usize, Challenger>(
+        &self,
+        challenger: Challenger,
+    ) -> Result<HidingKeccakConfig<F, Dft, D, Challenger>, UnsupportedConfig>
+    where
+        F: TwoAdicField + BinomiallyExtendable<D> + HasTwoAdicBinomialExtension<D>,
+        Dft: TwoAdicSubgroupDft<F> + Default,
+    {
+        // Only the prover draws randomness: verifiers, which may be built without access to the OS
+        // randomness, use a fixed seed
+        #[cfg(feature = "prover")]
+        let rng = ZkRng::from_os_rng;
+        #[cfg(not(feature = "prover"))]
+        let rng = || ZkRng::seed_from_u64(0);
+        let byte_hash = ByteHash {};
+        let val_mmcs =
+            HidingValMmcs::new(FieldHash::new(byte_hash), MyCompress::new(byte_hash), rng());
+        let pcs = HidingPcs::new(
+            Dft::default(),
+            val_mmcs.clone(),
+            self.fri_config::<_, D>(HidingChallengeMmcs::new(val_mmcs))?,
+            NUM_RANDOM_CODEWORDS,
+            rng(),
+        );
+        Ok(HidingKeccakConfig::new(pcs, challenger))
+    }
+
+    /// The STARK configuration hashing with the Poseidon2 permutation `perm`, over the degree `D` extension
+    /// of `F`, with `Dft` computing the low-degree extensions. Fails if `D` is not the extension degree of
+    /// this configuration.
+    pub fn poseidon2_config<F, Perm, Dft, const D: usize>(
+        &self,
+        perm: Perm,
+    ) -> Result<Poseidon2Config<F, Perm, Dft, D>, UnsupportedConfig>
+    where
+        F: TwoAdicField + BinomiallyExtendable<D> + HasTwoAdicBinomialExtension<D>,
+        Perm: CryptographicPermutation<[F; 16]>,
+        Dft: TwoAdicSubgroupDft<F> + Default,
+    {
+        let val_mmcs = Poseidon2ValMmcs::new(
+            Poseidon2Hash::new(perm.clone()),
+            Poseidon2Compress::new(perm.clone()),
+        );
+        let pcs = Poseidon2Pcs::new(
+            Dft::default(),
+            val_mmcs.clone(),
+            self.fri_config::<_, D>(Poseidon2ChallengeMmcs::new(val_mmcs))?,
+        );
+        Ok(Poseidon2Config::new(pcs, Poseidon2Challenger::new(perm)))
+    }
+
+    /// The STARK configuration with the Circle STARK PCS hashing with Keccak256, over the degree `D`
+    /// extension of `F`, with `challenger` the Keccak256 challenger for `F`. Fails if `D` is not the
+    /// extension degree of this configuration.
+    pub fn circle_config<F, const D: usize, Challenger>(
+        &self,
+        challenger: Challenger,
+    ) -> Result<CircleKeccakConfig<F, D, Challenger>, UnsupportedConfig>
+    where
+        F: ComplexExtendable + BinomiallyExtendable<D>,
+    {
+        let byte_hash = ByteHash {};
+        let val_mmcs = ValMmcs::new(FieldHash::new(byte_hash), MyCompress::new(byte_hash));
+        let pcs = CircleKeccakPcs {
+            mmcs: val_mmcs.clone(),
+            fri_config: self.fri_config::<_, D>(ChallengeMmcs::new(val_mmcs))?,
+            _phantom: PhantomData,
+        };
+        Ok(CircleKeccakConfig::new(pcs, challenger))
+    }
+
+    fn fri_config<M, const D: usize>(&self, mmcs: M) -> Result<FriConfig<M>, UnsupportedConfig> {
+        if D != self.extension_degree.degree() {
+            return Err(UnsupportedConfig::WrongExtensionDegree(
+                self.extension_degree,
+                D,
+            ));
+        }
+        Ok(FriConfig {
+            log_blowup: self.log_blowup,
+            log_final_poly_len: self.log_final_poly_len,
+            num_queries: self.num_queries,
+            proof_of_work_bits: self.proof_of_work_bits,
+            mmcs,
+        })
+    }
+}
+
+impl Default for CollatzStarkConfig {
+    fn default() -> Self {
+        Self::from_preset(SecurityPreset::default())
+    }
+}
+
+/// Evaluates `$body` with `$val` bound to the base field selected by the [`CollatzStarkConfig`] `$config`.
+macro_rules! with_field {
+    ($config:expr, |$val:ident| $body:expr) => {{
+        use $crate::config::FieldChoice;
+        match $config.field() {
+            FieldChoice::BabyBear => {
+                type $val = p3_baby_bear::BabyBear;
+                $body
+            }
+            FieldChoice::KoalaBear => {
+                type $val = p3_koala_bear::KoalaBear;
+                $body
+            }
+            FieldChoice::Goldilocks => {
+                type $val = p3_goldilocks::Goldilocks;
+                $body
+            }
+            FieldChoice::Mersenne31 => {
+                type $val = p3_mersenne_31::Mersenne31;
+                $body
+            }
+        }
+    }};
+}
+
+/// Evaluates `$body` with `$stark` bound to the STARK configuration selected by the
+/// [`CollatzStarkConfig`] `$config`, and `$val` to its base field, returning its value, or the error of
+/// [`CollatzStarkConfig::check`] if the configuration does not pass it.
+macro_rules! with_stark_config {
+    ($config:expr, |$stark:ident, $val:ident| $body:expr) => {{
+        use $crate::config::{
+            ByteHash, Challenger32, Challenger64, CollatzStarkConfig, DftChoice, ExtensionDegree,
+            FieldChoice, HashChoice, Radix2Bowers, Radix2DitParallel, UnsupportedConfig,
+        };
+        let config: &CollatzStarkConfig = &$config;
+        macro_rules! with_stark {
+            ($stark_config:expr) => {
+                match $stark_config {
+                    Ok($stark) => Ok($body),
+                    Err(error) => Err(error),
+                }
+            };
+        }
+        // Each choice binds a type, or the extension degree, for the following ones
+        macro_rules! with_dft {
+            (|$dft:ident| $inner:expr) => {
+                match config.dft() {
+                    DftChoice::Radix2Bowers => {
+                        type $dft = Radix2Bowers;
+                        $inner
+                    }
+                    DftChoice::Radix2DitParallel => {
+                        type $dft = Radix2DitParallel<$val>;
+                        $inner
+                    }
+                }
+            };
+        }
+        macro_rules! with_keccak {
+            ($challenger:ty, $dft:ty, $degree:ident) => {{
+                let challenger = <$challenger>::from_hasher(vec![], ByteHash {});
+                if config.zk() {
+                    with_stark!(config.hiding_keccak_config::<$val, $dft, $degree, _>(challenger))
+                } else {
+                    with_stark!(config.keccak_config::<$val, $dft, $degree, _>(challenger))
+                }
+            }};
+        }
+        // The round constants of the permutations are fixed, as the prover and the verifier must use the
+        // same ones
+        macro_rules! with_hash {
+            ($challenger:ty, $perm:expr, $dft:ty, $degree:ident) => {
+                match config.hash() {
+                    HashChoice::Keccak256 => with_keccak!($challenger, $dft, $degree),
+                    HashChoice::Poseidon2 => {
+                        with_stark!(config.poseidon2_config::<$val, _, $dft, $degree>($perm))
+                    }
+                }
+            };
+        }
+        match (config.check(), config.field(), config.extension_degree()) {
+            (Err(error), _, _) => Err(error),
+            (Ok(()), FieldChoice::BabyBear, ExtensionDegree::Four) => {
+                type $val = p3_baby_bear::BabyBear;
+                const D: usize = 4;
+                with_dft!(|Dft| with_hash!(
+                    Challenger32<$val>,
+                    p3_baby_bear::default_babybear_poseidon2_16(),
+                    Dft,
+                    D
+                ))
+            }
+            (Ok(()), FieldChoice::BabyBear, ExtensionDegree::Five) => {
+                type $val = p3_baby_bear::BabyBear;
+                const D: usize = 5;
+                with_dft!(|Dft| with_hash!(
+                    Challenger32<$val>,
+                    p3_baby_bear::default_babybear_poseidon2_16(),
+                    Dft,
+                    D
+                ))
+            }
+            (Ok(()), FieldChoice::KoalaBear, ExtensionDegree::Four) => {
+                type $val = p3_koala_bear::KoalaBear;
+                const D: usize = 4;
+                with_dft!(|Dft| with_hash!(
+                    Challenger32<$val>,
+                    p3_koala_bear::default_koalabear_poseidon2_16(),
+                    Dft,
+                    D
+                ))
+            }
+            (Ok(()), FieldChoice::Goldilocks, ExtensionDegree::Two) => {
+                type $val = p3_goldilocks::Goldilocks;
+                const D: usize = 2;
+                with_dft!(|Dft| with_keccak!(Challenger64<$val>, Dft, D))
+            }
+            (Ok(()), FieldChoice::Mersenne31, ExtensionDegree::Three) => {
+                type $val = p3_mersenne_31::Mersenne31;
+                const D: usize = 3;
+                let challenger = Challenger32::<$val>::from_hasher(vec![], ByteHash {});
+                with_stark!(config.circle_config::<$val, D, _>(challenger))
+            }
+            // `check` rejects the other extension degrees
+            (Ok(()), field, degree) => Err(UnsupportedConfig::ExtensionDegree(field, degree)),
+        }
+    }};
+}
+
+pub(crate) use {with_field, with_stark_config};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_provide_their_security() {
+        for preset in SecurityPreset::ALL {
+            let config = CollatzStarkConfig::from_preset(preset);
+            for trace_length in [config.min_trace_length(), 1 << 20] {
+                let security = config.security_report(trace_length);
+                assert!(
+                    security.conjectured >= preset.min_conjectured_security(),
+                    "{preset:?}: {security:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parameters_round_trip() {
+        for preset in SecurityPreset::ALL {
+            let config = CollatzStarkConfig::from_preset(preset);
+            for config in [
+                config,
+                config.with_dft(DftChoice::Radix2DitParallel),
+                config.with_zk(true),
+                config.with_log_blowup(2).with_num_queries(8),
+                config.with_proof_of_work_bits(0),
+            ] {
+                assert_eq!(
+                    CollatzStarkConfig::from_parameters(&config.proof_parameters()),
+                    Some(config)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_supported_choice_round_trips() {
+        let testing = CollatzStarkConfig::from_preset(SecurityPreset::Testing);
+        for field in FieldChoice::ALL {
+            for &degree in field.extension_degrees() {
+                for hash in HashChoice::ALL {
+                    let config = testing
+                        .with_field(field)
+                        .with_extension_degree(degree)
+                        .with_hash(hash);
+                    let parameters = config.proof_parameters();
+                    if config.check().is_ok() {
+                        assert_eq!(
+                            CollatzStarkConfig::from_parameters(&parameters),
+                            Some(config)
+                        );
+                    } else {
+                        // e.g. Poseidon2 over Goldilocks, which has no Poseidon2 configuration
+                        assert!(!field.hashes().contains(&hash), "{config:?}");
+                        assert_eq!(CollatzStarkConfig::from_parameters(&parameters), None);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn poseidon2_proofs_are_not_zero_knowledge() {
+        let poseidon2 = CollatzStarkConfig::default().with_hash(HashChoice::Poseidon2);
+        assert!(poseidon2.check().is_ok());
+        assert_eq!(
+            poseidon2.with_zk(true).check(),
+            Err(UnsupportedConfig::ZeroKnowledge(
+                FieldChoice::BabyBear,
+                HashChoice::Poseidon2
+            ))
+        );
+    }
+
+    #[test]
+    fn from_parameters_rejects_hostile_values() {
+        let parameters = CollatzStarkConfig::default().proof_parameters();
+
+        // Out of bounds FRI parameters, which would otherwise set the verifier's work
+        let fri = parameters.fri;
+        for fri in [
+            FriParameters {
+                num_queries: u32::MAX,
+                ..fri
+            },
+            FriParameters {
+                num_queries: 0,
+                ..fri
+            },
+            FriParameters {
+                log_blowup: u32::MAX,
+                ..fri
+            },
+            FriParameters {
+                proof_of_work_bits: u32::MAX,
+                ..fri
+            },
+            FriParameters {
+                log_final_poly_len: u32::MAX,
+                ..fri
+            },
+        ] {
+            let parameters = ProofParameters {
+                fri,
+                ..parameters.clone()
+            };
+            assert_eq!(
+                CollatzStarkConfig::from_parameters(&parameters),
+                None,
+                "{fri}"
+            );
+        }
+
+        // The choices must be recorded exactly, not merely contained in the description
+        let pcs = &parameters.pcs;
+        for pcs in [
+            format!("{pcs}, num_queries: 1"),
+            pcs.replace("Radix2Bowers", "Radix2Bowers, log_blowup: 1"),
+            pcs.replace("Radix2Bowers", "Radix2DitParallel, Radix2Bowers"),
+        ] {
+            let parameters = ProofParameters {
+                pcs,
+                ..parameters.clone()
+            };
+            assert_eq!(
+                CollatzStarkConfig::from_parameters(&parameters),
+                None,
+                "{parameters}"
+            );
+        }
+    }
+}
@@ -0,0 +1,268 @@
+//! Row-level diagnostics for traces which do not satisfy the constraints.
+//!
+//! `p3_uni_stark::prove` only panics on such traces in debug builds, or produces a proof which does not
+//! verify. [`check_trace`] instead evaluates [`CollatzAir::eval`] on every row with a checking
+//! `AirBuilder`, and names each failing constraint along with the decoded rows it was evaluated on.
+
+use std::fmt;
+
+use collatz_core::{CollatzError, DecodedRow, TraceLayout};
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAirWithPublicValues};
+use p3_field::{Field, PrimeField64};
+use p3_matrix::dense::{RowMajorMatrix, RowMajorMatrixView};
+use p3_matrix::stack::VerticalPair;
+use p3_matrix::Matrix;
+
+use crate::air::CollatzAir;
+
+/// The constraints of [`CollatzAir`], in the order `eval` asserts them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Constraint {
+    /// Bit `i` of the first row is bit `i` of the starting value.
+    FirstRowBit(usize),
+    FirstRowStepCounter,
+    FirstRowTransitionFlag,
+    /// The carry bit in this column is zero on the first row.
+    FirstRowCarryBit(usize),
+    /// Bit `i` of the next row is binary.
+    BitConsistency(usize),
+    TransitionFlagConsistency,
+    /// The carry bit in this column of the next row is binary, and zero unless the next row is a transition.
+    CarryBitConsistency(usize),
+    /// The Collatz rule, or the repetition of the row, on limb `k`.
+    CollatzRule(usize),
+    StepCounter,
+    DoneFlagBinary,
+    /// The done flag is only set on 1.
+    DoneFlagOnlyOnOne,
+    /// The done flag is set on every 1.
+    DoneFlagOnEveryOne,
+    DoneInverseZero,
+    /// No transition follows a row which is done.
+    NoTransitionAfterDone,
+    /// Bit `i` of the last row is bit `i` of 1.
+    FinalRowBit(usize),
+    FinalRowStepCounter,
+}
+
+impl Constraint {
+    /// All constraints asserted by `eval` for `layout`, in order.
+    pub fn all(layout: &TraceLayout) -> Vec<Constraint> {
+        let n = layout.num_bits();
+        let mut constraints: Vec<_> = (0..n).map(Constraint::FirstRowBit).collect();
+        constraints.extend([
+            Constraint::FirstRowStepCounter,
+            Constraint::FirstRowTransitionFlag,
+        ]);
+        constraints.extend(layout.carry_columns().map(Constraint::FirstRowCarryBit));
+        constraints.extend((0..n).map(Constraint::BitConsistency));
+        constraints.push(Constraint::TransitionFlagConsistency);
+        constraints.extend(layout.carry_columns().map(Constraint::CarryBitConsistency));
+        constraints.extend((0..layout.num_limbs()).map(Constraint::CollatzRule));
+        constraints.extend([
+            Constraint::StepCounter,
+            Constraint::DoneFlagBinary,
+            Constraint::DoneFlagOnlyOnOne,
+            Constraint::DoneFlagOnEveryOne,
+            Constraint::DoneInverseZero,
+            Constraint::NoTransitionAfterDone,
+        ]);
+        constraints.extend((0..n).map(Constraint::FinalRowBit));
+        constraints.push(Constraint::FinalRowStepCounter);
+        constraints
+    }
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constraint::FirstRowBit(i) => write!(f, "boundary bit {i} of the first row"),
+            Constraint::FirstRowStepCounter => write!(f, "initial step counter"),
+            Constraint::FirstRowTransitionFlag => write!(f, "initial transition flag"),
+            Constraint::FirstRowCarryBit(column) => {
+                write!(f, "initial carry bit in column {column}")
+            }
+            Constraint::BitConsistency(i) => write!(f, "consistency of bit {i}"),
+            Constraint::TransitionFlagConsistency => {
+                write!(f, "consistency of the transition flag")
+            }
+            Constraint::CarryBitConsistency(column) => {
+                write!(f, "consistency of the carry bit in column {column}")
+            }
+            Constraint::CollatzRule(k) => write!(f, "Collatz rule on limb {k}"),
+            Constraint::StepCounter => write!(f, "step counter"),
+            Constraint::DoneFlagBinary => write!(f, "consistency of the done flag"),
+            Constraint::DoneFlagOnlyOnOne => write!(f, "done flag only on 1"),
+            Constraint::DoneFlagOnEveryOne => write!(f, "done flag on every 1"),
+            Constraint::DoneInverseZero => write!(f, "zero done inverse once done"),
+            Constraint::NoTransitionAfterDone => write!(f, "no transition once done"),
+            Constraint::FinalRowBit(i) => write!(f, "boundary bit {i} of the final row"),
+            Constraint::FinalRowStepCounter => write!(f, "final step counter"),
+        }
+    }
+}
+
+/// A constraint which does not hold on the window starting at `row`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConstraintFailure {
+    pub row: usize,
+    pub constraint: Constraint,
+    pub local: DecodedRow,
+    /// The following row, which is the first one for the last row.
+    pub next: DecodedRow,
+}
+
+impl fmt::Display for ConstraintFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} fails at row {} (local: {}; next: {})",
+            self.constraint, self.row, self.local, self.next
+        )
+    }
+}
+
+/// Evaluates the constraints of `air` on every row of `trace`, returning every failure in row order.
+/// An empty result means that the trace is valid for `public_values`, and an error that the trace or the
+/// public values do not have the shape `air` expects.
+pub fn check_trace<F: PrimeField64>(
+    air: &CollatzAir,
+    trace: &RowMajorMatrix<F>,
+    public_values: &[F],
+) -> Result<Vec<ConstraintFailure>, CollatzError> {
+    let layout = air.layout();
+    let height = trace.height();
+    if trace.width() != layout.width() || height == 0 {
+        return Err(CollatzError::TraceConstruction(format!(
+            "expected a non-empty trace of {} columns, got {} rows of {} columns",
+            layout.width(),
+            height,
+            trace.width()
+        )));
+    }
+    let row = |i: usize| &trace.values[i * trace.width()..(i + 1) * trace.width()];
+
+    let mut failures = Vec::new();
+    for i in 0..height {
+        let (local, next) = (row(i), row((i + 1) % height));
+        for constraint in failing_constraints(air, local, next, public_values, i, height)? {
+            failures.push(ConstraintFailure {
+                row: i,
+                constraint,
+                local: decode_row(&layout, local),
+                next: decode_row(&layout, next),
+            });
+        }
+    }
+    Ok(failures)
+}
+
+fn decode_row<F: PrimeField64>(layout: &TraceLayout, row: &[F]) -> DecodedRow {
+    layout.decode_row(|column| row[column].as_canonical_u64() as u128)
+}
+
+/// Constraints of `air` which do not hold on the window of rows `local` and `next`, where `local` is row
+/// `row` of a trace with `height` rows. Fails if the rows or the public values do not have the shape `air`
+/// expects.
+pub fn failing_constraints<F: Field>(
+    air: &CollatzAir,
+    local: &[F],
+    next: &[F],
+    public_values: &[F],
+    row: usize,
+    height: usize,
+) -> Result<Vec<Constraint>, CollatzError> {
+    let layout = air.layout();
+    if local.len() != layout.width() || next.len() != layout.width() {
+        return Err(CollatzError::TraceConstruction(format!(
+            "expected rows of {} columns, got {} and {}",
+            layout.width(),
+            local.len(),
+            next.len()
+        )));
+    }
+    let num_public_values = BaseAirWithPublicValues::<F>::num_public_values(air);
+    if public_values.len() != num_public_values {
+        return Err(CollatzError::TraceConstruction(format!(
+            "expected {num_public_values} public values, got {}",
+            public_values.len()
+        )));
+    }
+    if row >= height {
+        return Err(CollatzError::TraceConstruction(format!(
+            "row {row} is out of a trace of {height} rows"
+        )));
+    }
+
+    let mut builder = ConstraintChecker {
+        main: VerticalPair::new(
+            RowMajorMatrixView::new_row(local),
+            RowMajorMatrixView::new_row(next),
+        ),
+        public_values,
+        is_first_row: F::from_bool(row == 0),
+        is_last_row: F::from_bool(row + 1 == height),
+        is_transition: F::from_bool(row + 1 != height),
+        evaluated: 0,
+        failed: Vec::new(),
+    };
+    air.eval(&mut builder);
+
+    let constraints = Constraint::all(&layout);
+    debug_assert_eq!(
+        builder.evaluated,
+        constraints.len(),
+        "The constraint names are out of sync with `eval`"
+    );
+    Ok(builder.failed.into_iter().map(|i| constraints[i]).collect())
+}
+
+/// An `AirBuilder` over the values of a window, which records the index of every constraint that fails.
+struct ConstraintChecker<'a, F: Field> {
+    main: VerticalPair<RowMajorMatrixView<'a, F>, RowMajorMatrixView<'a, F>>,
+    public_values: &'a [F],
+    is_first_row: F,
+    is_last_row: F,
+    is_transition: F,
+    evaluated: usize,
+    failed: Vec<usize>,
+}
+
+impl<'a, F: Field> AirBuilder for ConstraintChecker<'a, F> {
+    type F = F;
+    type Expr = F;
+    type Var = F;
+    type M = VerticalPair<RowMajorMatrixView<'a, F>, RowMajorMatrixView<'a, F>>;
+
+    fn main(&self) -> Self::M {
+        self.main
+    }
+
+    fn is_first_row(&self) -> Self::Expr {
+        self.is_first_row
+    }
+
+    fn is_last_row(&self) -> Self::Expr {
+        self.is_last_row
+    }
+
+    fn is_transition_window(&self, size: usize) -> Self::Expr {
+        assert_eq!(size, 2, "Only windows of two rows are supported");
+        self.is_transition
+    }
+
+    fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
+        if x.into() != F::ZERO {
+            self.failed.push(self.evaluated);
+        }
+        self.evaluated += 1;
+    }
+}
+
+impl<F: Field> AirBuilderWithPublicValues for ConstraintChecker<'_, F> {
+    type PublicVar = F;
+
+    fn public_values(&self) -> &[Self::F] {
+        self.public_values
+    }
+}
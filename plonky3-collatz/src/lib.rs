@@ -0,0 +1,25 @@
+//! Collatz AIR, prover and verifier built on Plonky3's `p3-uni-stark`.
+//!
+//! The prover, and the trace generation it relies on, are behind the default `prover` feature:
+//! verifiers can depend on this crate with `default-features = false` to only get the AIR and [`verify`].
+
+pub mod air;
+#[cfg(feature = "prover")]
+pub mod backend;
+pub mod config;
+pub mod debug;
+#[cfg(feature = "prover")]
+pub mod prover;
+#[cfg(feature = "prover")]
+mod utils;
+pub mod verifier;
+
+pub use air::CollatzAir;
+#[cfg(feature = "prover")]
+pub use backend::Plonky3Backend;
+pub use debug::{check_trace, ConstraintFailure};
+#[cfg(feature = "prover")]
+pub use prover::{prove, prove_trace, prove_witness};
+#[cfg(feature = "prover")]
+pub use utils::generate_collatz_trace;
+pub use verifier::{verify, verify_min_security, VerifyError};
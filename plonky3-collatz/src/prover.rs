@@ -0,0 +1,79 @@
+use std::any::Any;
+
+use collatz_core::{
+    Backend, BigUint, CollatzClaim, CollatzError, CollatzWitness, ProofContainer, SequenceLimits,
+    TraceLayout,
+};
+use p3_field::Field;
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::air::CollatzAir;
+use crate::config::{with_field, with_stark_config, CollatzStarkConfig};
+use crate::debug::check_trace;
+use crate::utils::witness_trace;
+
+/// Proves that `starting_value` reaches 1, returning the proof in a container along with its claim.
+pub fn prove(
+    starting_value: BigUint,
+    limits: &SequenceLimits,
+    config: &CollatzStarkConfig,
+) -> Result<ProofContainer, CollatzError> {
+    prove_witness(CollatzWitness::new(starting_value, limits)?, config)
+}
+
+/// Proves the sequence of `witness`, padded to the minimum trace length of `config`, returning the proof in a
+/// container along with its claim.
+pub fn prove_witness(
+    witness: CollatzWitness,
+    config: &CollatzStarkConfig,
+) -> Result<ProofContainer, CollatzError> {
+    let witness = witness.with_min_trace_length(config.min_trace_length());
+    let claim = CollatzClaim::new(witness.starting_value().clone(), witness.steps());
+    with_field!(config, |Val| {
+        let (trace, layout) = witness_trace::<Val>(&witness);
+        prove_trace(trace, layout, claim, config)
+    })
+}
+
+/// Proves `trace`, laid out with `layout`, against `claim`, without checking that it is the honest trace
+/// of the claim. The trace must be over the field of `config`, with the limbs the verifier expects there
+/// (see [`TraceLayout::for_field`]). Traces which do not satisfy the constraints are rejected in debug
+/// builds, where Plonky3 would panic on them, and proven in release builds, where their proofs do not verify.
+pub fn prove_trace<F: Field>(
+    trace: RowMajorMatrix<F>,
+    layout: TraceLayout,
+    claim: CollatzClaim,
+    config: &CollatzStarkConfig,
+) -> Result<ProofContainer, CollatzError> {
+    let proof = with_stark_config!(config, |stark, Val| {
+        let trace = (Box::new(trace) as Box<dyn Any>)
+            .downcast::<RowMajorMatrix<Val>>()
+            .map_err(|_| {
+                CollatzError::TraceConstruction(format!(
+                    "the trace is not over {:?}",
+                    config.field()
+                ))
+            })?;
+        let air = CollatzAir::new::<Val>(layout)?;
+        // The claim is passed as public values, which both the prover and the verifier feed to the challenger
+        let public_values = air.public_values::<Val>(&claim.starting_value, claim.steps_count)?;
+        // `p3_uni_stark::prove` panics on traces which do not satisfy the constraints in debug builds
+        if cfg!(debug_assertions) {
+            if let Some(failure) = check_trace(&air, &trace, &public_values)?.first() {
+                return Err(CollatzError::Prover(failure.to_string().into()));
+            }
+        }
+        let proof = p3_uni_stark::prove(&stark, &air, *trace, &public_values);
+        postcard::to_allocvec(&proof)
+            .map_err(|error| CollatzError::Prover(error.to_string().into()))?
+    })
+    .map_err(|error| CollatzError::Prover(Box::new(error)))?;
+
+    Ok(ProofContainer {
+        backend: Backend::Plonky3,
+        parameters: config.proof_parameters(),
+        claim,
+        num_bits: layout.num_bits() as u32,
+        proof,
+    })
+}
@@ -0,0 +1,99 @@
+use std::fmt;
+
+use collatz_core::{Backend, CollatzError, ProofContainer, ProofFormatError, TraceLayout};
+use p3_uni_stark::Proof;
+
+use crate::air::CollatzAir;
+use crate::config::{with_stark_config, CollatzStarkConfig, UnsupportedConfig};
+
+/// Reasons why a proof container is rejected.
+#[derive(Debug)]
+pub enum VerifyError {
+    /// The verifier's configuration cannot be instantiated.
+    Config(UnsupportedConfig),
+    /// The configuration recorded in the container provides less than the required conjectured security.
+    InsufficientSecurity { conjectured: u32, required: u32 },
+    /// The container was not produced by this backend with the expected parameters, or is inconsistent.
+    Format(ProofFormatError),
+    /// The proof bytes are not a valid Plonky3 proof.
+    Deserialization(postcard::Error),
+    /// The proof does not prove the container's claim, for the reason reported by Plonky3.
+    Verification(String),
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::Config(error) => write!(f, "unsupported configuration: {error}"),
+            VerifyError::InsufficientSecurity {
+                conjectured,
+                required,
+            } => write!(
+                f,
+                "the proof has {conjectured} bits of conjectured security, fewer than the required {required}"
+            ),
+            VerifyError::Format(error) => write!(f, "{error}"),
+            VerifyError::Deserialization(error) => write!(f, "invalid proof: {error}"),
+            VerifyError::Verification(error) => write!(f, "verification failed: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+impl From<VerifyError> for CollatzError {
+    fn from(error: VerifyError) -> Self {
+        CollatzError::Verification(Box::new(error))
+    }
+}
+
+/// Verifies the proof in `container` against its claim, with the configuration recorded in the container
+/// (see [`CollatzStarkConfig::from_parameters`]), accepting proofs produced with any configuration which
+/// provides at least `min_conjectured_security` bits of conjectured security for the claim's trace length.
+pub fn verify_min_security(
+    container: &ProofContainer,
+    min_conjectured_security: u32,
+) -> Result<(), VerifyError> {
+    let config = CollatzStarkConfig::from_parameters(&container.parameters).ok_or(
+        VerifyError::Format(ProofFormatError::Malformed("parameters")),
+    )?;
+    let trace_length = container.claim.trace_length(config.min_trace_length());
+    let conjectured = config.security_report(trace_length).conjectured;
+    if conjectured < min_conjectured_security {
+        return Err(VerifyError::InsufficientSecurity {
+            conjectured,
+            required: min_conjectured_security,
+        });
+    }
+    verify(container, &config)
+}
+
+/// Verifies the proof in `container` against its claim, accepting only proofs produced with `config`.
+pub fn verify(container: &ProofContainer, config: &CollatzStarkConfig) -> Result<(), VerifyError> {
+    config.check().map_err(VerifyError::Config)?;
+    container
+        .check(Backend::Plonky3, &config.proof_parameters())
+        .map_err(VerifyError::Format)?;
+
+    // The verifier must use the prover's layout, which is only recorded in the container
+    // It must be the layout of the proof's trace, be usable over the field and fit the claim, whatever the
+    // container says. The number of bits is checked against the width of the opened trace before the public
+    // values, one per bit, are allocated.
+    let malformed = || VerifyError::Format(ProofFormatError::Malformed("num_bits"));
+
+    with_stark_config!(config, |stark, Val| {
+        let proof: Proof<_> =
+            postcard::from_bytes(&container.proof).map_err(VerifyError::Deserialization)?;
+        let layout = TraceLayout::new(container.num_bits as usize).for_field(config.field().bits());
+        let layout = Some(layout)
+            .filter(|layout| layout.width() == proof.opened_values.trace_local.len())
+            .ok_or_else(malformed)?;
+        let air = CollatzAir::new::<Val>(layout).map_err(|_| malformed())?;
+        let public_values = air
+            .public_values::<Val>(&container.claim.starting_value, container.claim.steps_count)
+            .map_err(|_| malformed())?;
+        p3_uni_stark::verify(&stark, &air, &proof, &public_values)
+            .map_err(|error| VerifyError::Verification(format!("{error:?}")))
+    })
+    .map_err(VerifyError::Config)?
+}
@@ -1,51 +1,188 @@
-use crate::utils::is_binary;
+use crate::config::{zk_fits, zk_random_rows, MASK_SELECTOR_POWERS, NUM_MASK_COLUMNS};
 use crate::utils::PublicInputs;
-use winterfell::{
-    math::{fields::f128::BaseElement, FieldElement},
-    Air, AirContext, Assertion, EvaluationFrame, TransitionConstraintDegree,
+use crate::utils::{carry, done_sum, is_binary, limb};
+use collatz_core::{CollatzError, TraceLayout};
+use winter_air::{
+    Air, AirContext, Assertion, EvaluationFrame, ProofOptions, TraceInfo,
+    TransitionConstraintDegree,
 };
+use winter_math::{ExtensibleField, FieldElement, StarkField};
+
+/// Number of rows of the periodic selector column which offsets the constraints (see [`CollatzAir`]).
+pub const SELECTOR_CYCLE: usize = 4;
 
 /// AIR for proving Collatz conjecture sequences.
-/// The trace consists of N columns, each representing a bit in the binary representation (LSB first)
-/// of the current number in the sequence, plus two additional columns:
+/// The trace columns are described by [`TraceLayout`]: N columns, each representing a bit in the binary
+/// representation (LSB first) of the current number in the sequence, plus additional columns:
 /// - Column N: step counter
 /// - Column N+1: transition flag (1 = transition, 0 = repeat)
-pub struct CollatzAir<const N: usize> {
-    context: AirContext<BaseElement>,
-    first: [BaseElement; N],
-    steps_count: BaseElement,
+/// - Column N+2: done flag (1 iff the current number is 1), which forbids any further transition
+/// - Column N+3: inverse witness for the done flag
+/// - Columns N+4..: carry bits between the 16-bit limbs of the current number
+///
+/// N is not fixed at compile time: it is recovered from the width of the trace in `TraceInfo`.
+///
+/// The base field `B` is the one of the prover's and the verifier's hasher (see
+/// [`crate::config::Primitives`]).
+///
+/// Winterfell's prover checks in debug builds that each transition constraint has *exactly* its declared
+/// degree over the trace, whereas the degree of ours depends on the trace: a constraint on a column which is
+/// constant (e.g. a bit above the largest value of the sequence, or a carry which is never set) vanishes
+/// identically. Each constraint of degree `d` is therefore offset by `S^(d+1) - S`, where `S` is a periodic
+/// column of [`SELECTOR_CYCLE`] rows which is 1 on the first row of each cycle and 0 on the others. The
+/// offset is zero on every row, so the constraints are unchanged on the trace, but it has degree exactly
+/// `3 * (d + 1) * trace_length / 4`, above the `d * (trace_length - 1)` of any trace for `d <= 3`: the
+/// constraint takes the degree of the offset, whatever the trace. As the column is periodic, the verifier
+/// only interpolates its [`SELECTOR_CYCLE`] values, whatever the length of the trace.
+///
+/// Zero-knowledge traces (see [`PublicInputs::zk`]) end with random rows, as many as
+/// [`zk_random_rows`], which mask the values of the trace a proof reveals. The sequence ends on the row
+/// before them, which the final assertions apply to, and the transitions from it are exempt from the
+/// constraints. They also have [`NUM_MASK_COLUMNS`] mask columns, which must be zero wherever the constraints
+/// apply and are random on the exempt rows: the constraint of mask column `j`, the column times `S` to the
+/// power [`MASK_SELECTOR_POWERS`]`[j]`, adds a random polynomial to the columns of the constraint composition
+/// polynomial up to the `j`-th, which masks the values of the constraint composition a proof reveals.
+pub struct CollatzAir<B: StarkField> {
+    context: AirContext<B>,
+    /// `None` if [`CollatzAir::check`] fails, in which case no trace satisfies the AIR.
+    layout: Option<TraceLayout>,
+    /// The degree of each transition constraint in the trace, in the order `evaluate_transition` writes
+    /// them, before the offset.
+    degrees: Vec<usize>,
+    first: Vec<B>,
+    steps_count: B,
+    /// Number of random rows at the end of zero-knowledge traces, or 0.
+    random_rows: usize,
+}
+
+impl<B: StarkField> CollatzAir<B> {
+    /// Checks that the AIR can be instantiated for `trace_info`, `pub_inputs` and `options`, returning the
+    /// trace layout, without the mask columns.
+    ///
+    /// [`Air::new`] cannot fail, so on the errors reported here it builds an AIR which no trace satisfies:
+    /// provers and verifiers must call this first to report why a trace or a proof is rejected.
+    pub fn check(
+        trace_info: &TraceInfo,
+        pub_inputs: &PublicInputs,
+        options: &ProofOptions,
+    ) -> Result<TraceLayout, CollatzError> {
+        let num_mask_columns = if pub_inputs.zk { NUM_MASK_COLUMNS } else { 0 };
+        let layout = trace_info
+            .width()
+            .checked_sub(num_mask_columns)
+            .and_then(TraceLayout::from_width)
+            .ok_or_else(|| {
+                CollatzError::TraceConstruction(format!(
+                    "no layout has {} columns",
+                    trace_info.width()
+                ))
+            })?;
+        layout.check_field_bits(B::MODULUS_BITS)?;
+        layout.check_fits(&pub_inputs.starting_value)?;
+        if pub_inputs.zk && !zk_fits(options, trace_info.length()) {
+            return Err(CollatzError::TraceConstruction(format!(
+                "a zero-knowledge trace of {} rows has no room for its {} random rows",
+                trace_info.length(),
+                zk_random_rows(options, trace_info.length())
+            )));
+        }
+        Ok(layout)
+    }
+
+    /// Number of random rows at the end of the trace, which is only non-zero for zero-knowledge traces.
+    pub fn random_rows(&self) -> usize {
+        self.random_rows
+    }
+
+    /// The values of the periodic columns on `row`, for evaluating the transition constraints outside of
+    /// Winterfell's prover and verifier.
+    pub fn periodic_values(&self, row: usize) -> Vec<B> {
+        vec![if row % SELECTOR_CYCLE == 0 {
+            B::ONE
+        } else {
+            B::ZERO
+        }]
+    }
 }
 
-impl<const N: usize> Air for CollatzAir<N> {
-    type BaseField = BaseElement;
-    type PublicInputs = PublicInputs<N>;
-
-    fn new(
-        trace_info: winterfell::TraceInfo,
-        pub_inputs: Self::PublicInputs,
-        options: winterfell::ProofOptions,
-    ) -> Self {
-        assert_eq!(N + 2, trace_info.width());
-        // We have N consistency constraints for binary values, plus 1 for the transition flag
-        let mut transition_constraints = vec![TransitionConstraintDegree::new(2); N + 1];
-
-        // Main transition constraint, multiplies the `is_transition` column (degree 1) by the weighted sum of the other columns (degree 1) and the parity bit (first column, degree 1), resulting in degree 3 constraint.
-        transition_constraints.push(TransitionConstraintDegree::new(3));
+impl<B> Air for CollatzAir<B>
+where
+    B: StarkField + ExtensibleField<2> + ExtensibleField<3>,
+{
+    type BaseField = B;
+    type PublicInputs = PublicInputs;
+
+    fn new(trace_info: TraceInfo, pub_inputs: Self::PublicInputs, options: ProofOptions) -> Self {
+        let Ok(layout) = Self::check(&trace_info, &pub_inputs, &options) else {
+            // A single transition constraint which never holds, and an assertion on the first cell
+            return CollatzAir {
+                context: AirContext::new(
+                    trace_info,
+                    vec![TransitionConstraintDegree::new(1)],
+                    1,
+                    options,
+                ),
+                layout: None,
+                degrees: vec![1],
+                first: Vec::new(),
+                steps_count: B::ZERO,
+                random_rows: 0,
+            };
+        };
+        let n = layout.num_bits();
+        let num_carry_bits = layout.carry_columns().len();
+        // We have N consistency constraints for binary values, plus 1 for the transition flag, plus 1 for each carry bit
+        let mut degrees = vec![2; n + 1 + num_carry_bits];
+
+        // Main transition constraint for each limb, multiplies the `is_transition` column (degree 1) by the weighted sum of the limb's columns (degree 1) and the parity bit (first column, degree 1), resulting in degree 3 constraints.
+        degrees.extend(vec![3; layout.num_limbs()]);
         // Step counter constraint (degree 2)
-        transition_constraints.push(TransitionConstraintDegree::new(2));
+        degrees.push(2);
+        // Done flag constraints: binary (degree 2), set only on 1 (degree 2), set on every 1 (degree 3),
+        // zero inverse when set (degree 2), and no transition once set (degree 2)
+        degrees.extend([2, 2, 3, 2, 2]);
+        // The offset S^(d+1) - S of each constraint only depends on the selector (see `CollatzAir`)
+        let mut transition_constraints: Vec<_> = degrees
+            .iter()
+            .map(|&degree| {
+                TransitionConstraintDegree::with_cycles(0, vec![SELECTOR_CYCLE; degree + 1])
+            })
+            .collect();
+        // Each mask column, random on the exempt rows, times a power of the selector
+        let random_rows = if pub_inputs.zk {
+            transition_constraints.extend(MASK_SELECTOR_POWERS.map(|power| match power {
+                0 => TransitionConstraintDegree::new(1),
+                _ => TransitionConstraintDegree::with_cycles(1, vec![SELECTOR_CYCLE; power]),
+            }));
+            zk_random_rows(&options, trace_info.length())
+        } else {
+            0
+        };
 
-        // We have 2*N boundary constraints for values, + 1 for initial step counter, + 1 for final step counter, + 1 for the initial transition flag
-        let num_boundary_constraints = 2 * N + 3;
+        // We have 2*N boundary constraints for values, + 1 for initial step counter, + 1 for final step counter, + 1 for the initial transition flag, + 1 for each initial carry bit, + 2 for the final done flag and inverse
+        let num_boundary_constraints = 2 * n + 5 + num_carry_bits;
+
+        let mut context = AirContext::new(
+            trace_info,
+            transition_constraints,
+            num_boundary_constraints,
+            options,
+        );
+        if random_rows > 0 {
+            // The transitions from the last row of the sequence, and from the random rows
+            context = context.set_num_transition_exemptions(random_rows + 1);
+        }
 
         CollatzAir {
-            context: AirContext::new(
-                trace_info,
-                transition_constraints,
-                num_boundary_constraints,
-                options,
-            ),
-            first: pub_inputs.values,
-            steps_count: pub_inputs.steps_count,
+            context,
+            layout: Some(layout),
+            degrees,
+            first: layout
+                .value_bits(&pub_inputs.starting_value)
+                .map(|bit| B::from(bit as u32))
+                .collect(),
+            steps_count: B::from(pub_inputs.steps_count),
+            random_rows,
         }
     }
 
@@ -56,34 +193,39 @@ impl<const N: usize> Air for CollatzAir<N> {
     fn evaluate_transition<E: FieldElement<BaseField = Self::BaseField>>(
         &self,
         frame: &EvaluationFrame<E>,
-        _periodic_values: &[E],
+        periodic_values: &[E],
         result: &mut [E],
     ) {
+        let Some(layout) = &self.layout else {
+            result[0] = E::ONE;
+            return;
+        };
+        let n = layout.num_bits();
         let current = frame.current();
         let next = frame.next();
 
-        let step_counter = current[N];
-        let next_step_counter = next[N];
-        let next_is_transition = next[N + 1];
+        let step_counter = current[layout.step_counter()];
+        let next_step_counter = next[layout.step_counter()];
+        let next_is_transition = next[layout.transition_flag()];
 
         // Consistency constraint: ensure each cell in the binary decomposition column is indeed a bit.
-        for i in 0..N {
-            result[i] = is_binary(next[i]);
+        for i in 0..n {
+            result[i] = is_binary(next[layout.bit(i)]);
         }
 
         // Ensure transition flag is binary
-        result[N] = is_binary(next[N + 1]);
+        result[n] = is_binary(next_is_transition);
 
-        let current_weighted_sum = (0..N).fold(E::ZERO, |acc, i| {
-            acc + (E::from(2u32.pow(i as u32)) * current[i])
-        });
-        let next_weighted_sum = (0..N).fold(E::ZERO, |acc, i| {
-            acc + (E::from(2u32.pow(i as u32)) * next[i])
-        });
+        // Ensure each carry bit is binary, and zero on rows which are not transitions
+        let carries_start = n + 1;
+        for (i, column) in layout.carry_columns().enumerate() {
+            result[carries_start + i] = next[column] * (next[column] - next_is_transition);
+        }
 
-        let is_odd = current[0];
+        let is_odd = current[layout.bit(0)];
+        let two_pow_limb = (0..layout.limb_bits()).fold(E::ONE, |acc, _| acc.double());
 
-        // Main transition constraint: apply the collatz_rule OR repeat row
+        // Main transition constraint: apply the collatz_rule OR repeat row, limb by limb
         // (Needed to ensure valid transitions for the entire trace length, even when we pad with 1's to the next power of two).
         // Note, that while our prover fills the remainder of the trace with 1's, it actually doesn't matter *which* row is repeated.
         // E.g. For the Collatz sequence "4, 2, 1", the prover could fill the trace with (the binary representations of):
@@ -93,54 +235,139 @@ impl<const N: usize> Air for CollatzAir<N> {
         // and all should be accepted.
 
         // Collatz transition rule:
-        // next_weighted_sum =
-        //      is_odd * (current_weighted_sum * 3 + 1) +
-        //      (1 - is_odd) * (current_weighted_sum / 2)
+        // next =
+        //      is_odd * (current * 3 + 1) +
+        //      (1 - is_odd) * (current / 2)
         //
         // Note that since we can't have division, we multiply all terms by 2, resulting in:
-        // 2 * next_weighted_sum =
-        //      is_odd * 2 * (current_weighted_sum * 3 + 1) +
-        //      (1 - is_odd) * current_weighted_sum
-        result[N + 1] =
-            // Apply the Collatz transition rule
-            next_is_transition * (
-                (E::from(2u32) * next_weighted_sum)
-                - (is_odd * E::from(2u32) * (current_weighted_sum * E::from(3u32) + E::ONE)
-                + (E::ONE - is_odd) * current_weighted_sum)
-            )
-            // No transition, repeat the current row
-            - (E::ONE - next_is_transition) * (next_weighted_sum - current_weighted_sum);
+        // 2 * next =
+        //      is_odd * 2 * (current * 3 + 1) +
+        //      (1 - is_odd) * current
+        //
+        // The weighted sum of the whole value would wrap around the field for large N, so the rule is
+        // applied to each limb k, with the carries moving the excess between limbs:
+        // 2 * next_k + 2^16 * carry_{k+1} =
+        //      is_odd * 2 * (current_k * 3 + [k == 0]) +
+        //      (1 - is_odd) * current_k + carry_k
+        let rule_start = carries_start + layout.carry_columns().len();
+        for k in 0..layout.num_limbs() {
+            let current_limb: E = limb(layout, current, k);
+            let next_limb: E = limb(layout, next, k);
+            let increment = if k == 0 { E::ONE } else { E::ZERO };
+
+            result[rule_start + k] =
+                // Apply the Collatz transition rule
+                next_is_transition * (
+                    (E::from(2u32) * next_limb + two_pow_limb * carry(layout, next, k + 1))
+                    - (is_odd * E::from(2u32) * (current_limb * E::from(3u32) + increment)
+                    + (E::ONE - is_odd) * current_limb
+                    + carry(layout, next, k))
+                )
+                // No transition, repeat the current row
+                - (E::ONE - next_is_transition) * (next_limb - current_limb);
+        }
 
         // Step counter constraint:
         // If next_is_transition = 1, increment step counter
         // If next_is_transition = 0, keep step counter the same
-        result[N + 2] =
+        result[rule_start + layout.num_limbs()] =
             // If there is a transition, then the step counter should be incremented
             next_is_transition * (next_step_counter - step_counter - E::ONE)
             // If there is no transition, then the step counter should be the same
             - (E::ONE - next_is_transition) * (next_step_counter - step_counter);
+
+        // Done flag constraints, on the current row (the last row is covered by the boundary constraints).
+        // The done sum is zero iff the current value is 1, so:
+        // - done = 1 forces the done sum to zero,
+        // - done = 0 forces the done sum to have an inverse, i.e. to be non-zero,
+        // and once done, the next row must not be a transition: the sequence stops at the first 1.
+        let done_start = rule_start + layout.num_limbs() + 1;
+        let is_done = current[layout.done_flag()];
+        let done_inverse = current[layout.done_inverse()];
+        let sum = done_sum(layout, current);
+        result[done_start] = is_binary(is_done);
+        result[done_start + 1] = is_done * sum;
+        result[done_start + 2] = (E::ONE - is_done) * (sum * done_inverse - E::ONE);
+        result[done_start + 3] = is_done * done_inverse;
+        result[done_start + 4] = is_done * next_is_transition;
+
+        // Offset each constraint of degree d by S^(d+1) - S, which is zero on every row (see `CollatzAir`)
+        let selector = periodic_values[0];
+        for (value, &degree) in result.iter_mut().zip(&self.degrees) {
+            let power = (0..degree).fold(selector, |acc, _| acc * selector);
+            *value += power - selector;
+        }
+
+        // Mask columns, which follow the layout's, are zero wherever the constraints apply
+        if self.random_rows > 0 {
+            let masks = &current[layout.width()..];
+            let results = &mut result[self.degrees.len()..];
+            for ((value, &mask), &power) in results.iter_mut().zip(masks).zip(&MASK_SELECTOR_POWERS)
+            {
+                *value = (0..power).fold(mask, |acc, _| acc * selector);
+            }
+        }
+    }
+
+    fn get_periodic_column_values(&self) -> Vec<Vec<Self::BaseField>> {
+        // S: 1 on the first row of each cycle, 0 on the others
+        let mut selector = vec![B::ZERO; SELECTOR_CYCLE];
+        selector[0] = B::ONE;
+        vec![selector]
     }
 
     fn get_assertions(&self) -> Vec<Assertion<Self::BaseField>> {
+        let Some(layout) = &self.layout else {
+            return vec![Assertion::single(0, 0, B::ZERO)];
+        };
+        let n = layout.num_bits();
         // Boundary constraint: the whole first row is the initial state
-        let mut assertions: Vec<Assertion<BaseElement>> = (0..N)
-            .map(|i| Assertion::single(i, 0, self.first[i]))
+        let mut assertions: Vec<Assertion<B>> = (0..n)
+            .map(|i| Assertion::single(layout.bit(i), 0, self.first[i]))
             .collect();
 
         // Initial step counter is 0
-        assertions.push(Assertion::single(N, 0, BaseElement::ZERO));
+        assertions.push(Assertion::single(layout.step_counter(), 0, B::ZERO));
         // Initial transition flag is 0 (not a transition)
-        assertions.push(Assertion::single(N + 1, 0, BaseElement::ZERO));
+        assertions.push(Assertion::single(layout.transition_flag(), 0, B::ZERO));
+        // Initial carries are 0 (not a transition)
+        for column in layout.carry_columns() {
+            assertions.push(Assertion::single(column, 0, B::ZERO));
+        }
 
-        let last_step = self.trace_length() - 1;
+        // The sequence ends before the random rows of zero-knowledge traces
+        let last_step = self.trace_length() - self.random_rows - 1;
         // Boundary constraint: the weighted sum of the last row is 1, i.e. the first column is 1, the rest are 0
-        assertions.push(Assertion::single(0, last_step, Self::BaseField::ONE));
-        for i in 1..N {
-            assertions.push(Assertion::single(i, last_step, Self::BaseField::ZERO));
+        assertions.push(Assertion::single(
+            layout.bit(0),
+            last_step,
+            Self::BaseField::ONE,
+        ));
+        for i in 1..n {
+            assertions.push(Assertion::single(
+                layout.bit(i),
+                last_step,
+                Self::BaseField::ZERO,
+            ));
         }
 
         // The last row's step counter should match the expected steps_count
-        assertions.push(Assertion::single(N, last_step, self.steps_count));
+        assertions.push(Assertion::single(
+            layout.step_counter(),
+            last_step,
+            self.steps_count,
+        ));
+        // The last row's value is 1, so it is done
+        assertions.push(Assertion::single(
+            layout.done_flag(),
+            last_step,
+            Self::BaseField::ONE,
+        ));
+        assertions.push(Assertion::single(
+            layout.done_inverse(),
+            last_step,
+            Self::BaseField::ZERO,
+        ));
 
         // We don't have an explicit ending boundary constraint for the last row's is_transition flag:
         // if the trace_length perfectly matches the steps_count without padding, then it's a transition row, otherwise it's not.
@@ -148,3 +375,33 @@ impl<const N: usize> Air for CollatzAir<N> {
         assertions
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use collatz_core::BigUint;
+    use winter_math::fields::f128::BaseElement;
+
+    use super::*;
+    use crate::config::default_proof_options;
+
+    #[test]
+    fn periodic_columns_do_not_grow_with_the_trace() {
+        // The periodic columns are the only part of the AIR the verifier builds for the whole trace: they
+        // must not depend on the trace length a proof declares
+        let layout = TraceLayout::new(16);
+        let periodic_columns = |trace_length: usize| {
+            let air = CollatzAir::<BaseElement>::new(
+                TraceInfo::new(layout.width(), trace_length),
+                PublicInputs::from((BigUint::from(27u32), 111)),
+                default_proof_options(),
+            );
+            air.get_periodic_column_values()
+        };
+        let shortest = periodic_columns(8);
+        assert_eq!(shortest.len(), 1);
+        assert_eq!(shortest[0].len(), SELECTOR_CYCLE);
+        for log_trace_length in [10, 20, 30] {
+            assert_eq!(periodic_columns(1 << log_trace_length), shortest);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use collatz_core::{
+    Backend, CollatzBackend, CollatzClaim, CollatzError, CollatzWitness, ProofContainer,
+    SequenceLimits,
+};
+use winter_air::ProofOptions;
+
+use crate::config::{Primitives, SecurityPreset};
+use crate::prover::prove_witness;
+use crate::verifier::verify;
+
+/// [`CollatzBackend`] proving with Winterfell, with fixed primitives and proof options.
+#[derive(Clone, Debug)]
+pub struct WinterfellBackend {
+    /// The preset the options are derived from, over the field of the primitives.
+    preset: Option<SecurityPreset>,
+    options: ProofOptions,
+    min_conjectured_security: u32,
+    primitives: Primitives,
+    limits: SequenceLimits,
+    zk: bool,
+}
+
+impl WinterfellBackend {
+    /// Proves with `options` and the default primitives, and only accepts proofs with at least
+    /// `min_conjectured_security` bits of conjectured security.
+    pub fn new(options: ProofOptions, min_conjectured_security: u32) -> Self {
+        Self {
+            preset: None,
+            options,
+            min_conjectured_security,
+            primitives: Primitives::default(),
+            limits: SequenceLimits::default(),
+            zk: false,
+        }
+    }
+
+    /// Proves with the options of `preset` over the field of the primitives, and only accepts proofs with
+    /// the conjectured security of `preset`.
+    pub fn from_preset(preset: SecurityPreset) -> Self {
+        let primitives = Primitives::default();
+        Self {
+            preset: Some(preset),
+            ..Self::new(
+                preset.proof_options(primitives.field),
+                preset.min_conjectured_security(),
+            )
+        }
+    }
+
+    /// Proves over the field and with the hash of `primitives`. Proofs with other primitives are still
+    /// accepted if they are secure enough.
+    pub fn with_primitives(mut self, primitives: Primitives) -> Self {
+        if let Some(preset) = self.preset {
+            self.options = preset.proof_options(primitives.field);
+        }
+        self.primitives = primitives;
+        self
+    }
+
+    /// Bounds the sequences this backend accepts to prove.
+    pub fn with_limits(mut self, limits: SequenceLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Requests zero-knowledge proofs, whose traces end with random rows and mask columns (see
+    /// [`crate::air::CollatzAir`]).
+    pub fn with_zk(mut self, zk: bool) -> Self {
+        self.zk = zk;
+        self
+    }
+}
+
+impl Default for WinterfellBackend {
+    fn default() -> Self {
+        Self::from_preset(SecurityPreset::default())
+    }
+}
+
+impl CollatzBackend for WinterfellBackend {
+    type Proof = ProofContainer;
+
+    fn backend(&self) -> Backend {
+        Backend::Winterfell
+    }
+
+    fn prove(&self, claim: &CollatzClaim) -> Result<Self::Proof, CollatzError> {
+        let witness = CollatzWitness::new(claim.starting_value.clone(), &self.limits)?;
+        if witness.steps() != claim.steps_count {
+            return Err(CollatzError::FalseClaim {
+                claim: claim.clone(),
+                steps: witness.steps(),
+            });
+        }
+        prove_witness(witness, self.options.clone(), self.primitives, self.zk)
+    }
+
+    fn verify(&self, claim: &CollatzClaim, proof: &Self::Proof) -> Result<(), CollatzError> {
+        // Check the proof against the given claim, whatever the container says
+        let container = ProofContainer {
+            claim: claim.clone(),
+            ..proof.clone()
+        };
+        Ok(verify(&container, self.min_conjectured_security)?)
+    }
+}
@@ -0,0 +1,446 @@
+use std::fmt;
+
+use collatz_core::{ProofParameters, SecurityReport};
+use winter_air::{BatchingMethod, FieldExtension, ProofOptions, TraceInfo};
+use winter_crypto::hashers::{Blake3_256, Rp64_256, RpJive64_256, Sha3_256};
+use winter_crypto::{DefaultRandomCoin, Hasher as _, MerkleTree};
+use winter_math::fields::{f128, f62, f64};
+use winter_math::StarkField;
+
+use crate::utils;
+
+// Type definitions, for the default primitives
+pub type Hasher = Blake3_256<f128::BaseElement>;
+pub type Merkle = MerkleTree<Hasher>;
+pub type Coin = DefaultRandomCoin<Hasher>;
+
+/// The base field of the trace.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FieldChoice {
+    F62,
+    /// The field of the algebraic hashes.
+    F64,
+    #[default]
+    F128,
+}
+
+impl FieldChoice {
+    pub const ALL: [FieldChoice; 3] = [FieldChoice::F62, FieldChoice::F64, FieldChoice::F128];
+
+    /// Name of the field recorded in the proof container.
+    pub fn name(&self) -> &'static str {
+        match self {
+            FieldChoice::F62 => "f62",
+            FieldChoice::F64 => "f64",
+            FieldChoice::F128 => "f128",
+        }
+    }
+
+    /// Number of bits of the modulus of the field.
+    pub fn bits(&self) -> u32 {
+        match self {
+            FieldChoice::F62 => f62::BaseElement::MODULUS_BITS,
+            FieldChoice::F64 => f64::BaseElement::MODULUS_BITS,
+            FieldChoice::F128 => f128::BaseElement::MODULUS_BITS,
+        }
+    }
+}
+
+/// The hash of the Merkle trees and of the random coin.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HashChoice {
+    #[default]
+    Blake3,
+    Sha3,
+    /// Rescue Prime over f64, which is cheaper to verify in a recursive verifier.
+    Rp64,
+    /// Rescue Prime over f64 in a Jive compression mode, with cheaper Merkle trees than [`HashChoice::Rp64`].
+    RpJive64,
+}
+
+impl HashChoice {
+    pub const ALL: [HashChoice; 4] = [
+        HashChoice::Blake3,
+        HashChoice::Sha3,
+        HashChoice::Rp64,
+        HashChoice::RpJive64,
+    ];
+
+    /// Name of the hash recorded in the proof container.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashChoice::Blake3 => "Blake3_256",
+            HashChoice::Sha3 => "Sha3_256",
+            HashChoice::Rp64 => "Rp64_256",
+            HashChoice::RpJive64 => "RpJive64_256",
+        }
+    }
+
+    /// Collision resistance of the hash, in bits.
+    pub fn collision_resistance(&self) -> u32 {
+        match self {
+            HashChoice::Blake3 => Blake3_256::<f128::BaseElement>::COLLISION_RESISTANCE,
+            HashChoice::Sha3 => Sha3_256::<f128::BaseElement>::COLLISION_RESISTANCE,
+            HashChoice::Rp64 => Rp64_256::COLLISION_RESISTANCE,
+            HashChoice::RpJive64 => RpJive64_256::COLLISION_RESISTANCE,
+        }
+    }
+
+    /// Whether the hash can hash elements of `field`: the algebraic hashes are defined over f64 only.
+    pub fn supports(&self, field: FieldChoice) -> bool {
+        match self {
+            HashChoice::Blake3 | HashChoice::Sha3 => true,
+            HashChoice::Rp64 | HashChoice::RpJive64 => field == FieldChoice::F64,
+        }
+    }
+}
+
+/// The base field and the hash proofs are produced with, which are types in Winterfell: the prover and the
+/// verifier instantiate the hasher they select with `with_hasher!`, whose base field is the trace's.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Primitives {
+    pub field: FieldChoice,
+    pub hash: HashChoice,
+}
+
+impl Primitives {
+    pub fn new(field: FieldChoice, hash: HashChoice) -> Self {
+        Self { field, hash }
+    }
+
+    /// Every supported combination of a field and a hash.
+    pub fn all() -> impl Iterator<Item = Primitives> {
+        FieldChoice::ALL
+            .into_iter()
+            .flat_map(|field| HashChoice::ALL.map(|hash| Primitives::new(field, hash)))
+            .filter(|primitives| primitives.check().is_ok())
+    }
+
+    /// The primitives whose names are recorded in `parameters`.
+    pub fn from_parameters(parameters: &ProofParameters) -> Option<Primitives> {
+        let field = parameters.field.split(',').next();
+        Primitives::all().find(|primitives| {
+            field == Some(primitives.field.name()) && parameters.hash == primitives.hash.name()
+        })
+    }
+
+    /// Checks that the hash can hash elements of the field.
+    pub fn check(&self) -> Result<(), UnsupportedPrimitives> {
+        if self.hash.supports(self.field) {
+            Ok(())
+        } else {
+            Err(UnsupportedPrimitives(*self))
+        }
+    }
+}
+
+/// [`Primitives`] whose hash is not defined over their field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnsupportedPrimitives(pub Primitives);
+
+impl fmt::Display for UnsupportedPrimitives {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} is not defined over {}",
+            self.0.hash.name(),
+            self.0.field.name()
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedPrimitives {}
+
+/// Named proof options, by the security they provide (see [`security_report`]).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SecurityPreset {
+    /// Few queries for fast tests: about 20 bits of conjectured security.
+    Testing,
+    /// 100 bits of conjectured security, without grinding.
+    #[default]
+    Conjectured100,
+    /// 128 bits of proven security, at the cost of much larger proofs.
+    Proven128,
+}
+
+impl SecurityPreset {
+    pub const ALL: [SecurityPreset; 3] = [
+        SecurityPreset::Testing,
+        SecurityPreset::Conjectured100,
+        SecurityPreset::Proven128,
+    ];
+
+    /// Proof options of this preset over `field`.
+    ///
+    /// FRI is at most as secure as the extension field its challenges are drawn from is larger than the
+    /// evaluation domain, by a factor of the domain size squared for the proven security: the quadratic
+    /// extensions of [`FieldChoice::F62`] and [`FieldChoice::F64`] are too small for
+    /// [`SecurityPreset::Proven128`], which uses their cubic extensions instead.
+    pub fn proof_options(&self, field: FieldChoice) -> ProofOptions {
+        self.options(field.bits())
+    }
+
+    /// Proof options of this preset over a base field of `field_bits` bits.
+    pub(crate) fn options(&self, field_bits: u32) -> ProofOptions {
+        // (queries, blowup factor, grinding bits)
+        let (num_queries, blowup_factor, grinding_factor) = match self {
+            // 3 bits per query
+            SecurityPreset::Testing => (8, 8, 0),
+            SecurityPreset::Conjectured100 => (34, 8, 0),
+            // -log2((1 + 1/16) / 2) > 0.91 bits per query, plus the grinding
+            SecurityPreset::Proven128 => (120, 16, 20),
+        };
+        let field_extension = if *self == SecurityPreset::Proven128 && field_bits < 128 {
+            FieldExtension::Cubic
+        } else {
+            FieldExtension::Quadratic
+        };
+        ProofOptions::new(
+            num_queries,
+            blowup_factor,
+            grinding_factor,
+            field_extension,
+            4,
+            7,
+            BatchingMethod::Linear,
+            BatchingMethod::Linear,
+        )
+    }
+
+    /// Conjectured security the verifier requires of proofs produced with this preset.
+    pub fn min_conjectured_security(&self) -> u32 {
+        match self {
+            SecurityPreset::Testing => 20,
+            SecurityPreset::Conjectured100 => 100,
+            SecurityPreset::Proven128 => 128,
+        }
+    }
+}
+
+/// The proof options used by the example and the CLI.
+pub fn default_proof_options() -> ProofOptions {
+    SecurityPreset::default().proof_options(FieldChoice::default())
+}
+
+/// Grinding only counts towards the security of proofs with at least this many bits of query security, as
+/// in Winterfell.
+const GRINDING_CONTRIBUTION_FLOOR: u32 = 80;
+
+/// Conjectured and proven security of proofs of traces of `trace_length` rows produced with `primitives` and
+/// `options`.
+pub fn security_report(
+    primitives: Primitives,
+    options: &ProofOptions,
+    trace_length: usize,
+) -> SecurityReport {
+    let log_blowup = options.blowup_factor().ilog2();
+    let num_queries = options.num_queries() as u32;
+    let grinding_bits = if log_blowup * num_queries >= GRINDING_CONTRIBUTION_FLOOR {
+        options.grinding_factor()
+    } else {
+        0
+    };
+    SecurityReport::estimate(
+        primitives.field.bits() * options.field_extension().degree(),
+        (trace_length * options.blowup_factor()).ilog2(),
+        log_blowup,
+        num_queries,
+        grinding_bits,
+        primitives.hash.collision_resistance(),
+    )
+}
+
+/// Minimum number of rows of traces proven with `options`.
+///
+/// Winterfell rejects traces shorter than [`TraceInfo::MIN_TRACE_LENGTH`], and the query positions are
+/// drawn from the low-degree extension domain, which must have more positions than there are queries.
+pub fn min_trace_length(options: &ProofOptions) -> usize {
+    let min_lde_domain_size = options.num_queries() + 1;
+    TraceInfo::MIN_TRACE_LENGTH.max(min_lde_domain_size.div_ceil(options.blowup_factor()))
+}
+
+/// Number of mask columns of zero-knowledge traces, one per column of the constraint composition
+/// polynomial (see [`crate::CollatzAir`]).
+pub const NUM_MASK_COLUMNS: usize = 3;
+
+/// Power of the selector column each mask column is multiplied by in its constraint, which places its
+/// randomness in the column of the constraint composition polynomial of the same index, and the lower ones.
+pub const MASK_SELECTOR_POWERS: [usize; NUM_MASK_COLUMNS] = [0, 2, 3];
+
+/// Number of random rows at the end of zero-knowledge traces of `trace_length` rows proven with `options`.
+///
+/// Each column is masked by as many random values as there are values of it a proof reveals: its openings
+/// at the out-of-domain point and the next one, and at most one linear combination per value opened in
+/// each FRI layer, and per coefficient of the FRI remainder. The number of layers is bounded by the number
+/// of folds of the whole low-degree extension domain.
+pub fn zk_random_rows(options: &ProofOptions, trace_length: usize) -> usize {
+    let fri_options = options.to_fri_options();
+    let folding_factor = fri_options.folding_factor();
+    let lde_domain_size = trace_length * options.blowup_factor();
+    let num_fri_layers = lde_domain_size.ilog2().div_ceil(folding_factor.ilog2()) as usize;
+    options.num_queries() * folding_factor * num_fri_layers + fri_options.remainder_max_degree() + 3
+}
+
+/// Whether zero-knowledge traces of `trace_length` rows proven with `options` have room for their random
+/// rows: with the last row of the sequence, they must fit in a quarter of the trace, so that the randomness
+/// of each mask column stays within the columns of the constraint composition polynomial it masks.
+pub fn zk_fits(options: &ProofOptions, trace_length: usize) -> bool {
+    4 * (zk_random_rows(options, trace_length) + 1) <= trace_length
+}
+
+/// Number of rows of the zero-knowledge trace of a sequence of `sequence_length` rows proven with `options`:
+/// the shortest trace which fits the sequence, followed by its random rows.
+pub fn zk_trace_length(options: &ProofOptions, sequence_length: usize) -> usize {
+    let mut trace_length = min_trace_length(options)
+        .max(sequence_length)
+        .next_power_of_two();
+    while !zk_fits(options, trace_length)
+        || sequence_length + zk_random_rows(options, trace_length) > trace_length
+    {
+        trace_length *= 2;
+    }
+    trace_length
+}
+
+/// Parameters recorded in the proof container for proofs produced with `primitives` and `options`, with
+/// zero knowledge if `zk`.
+pub fn proof_parameters(
+    primitives: Primitives,
+    options: &ProofOptions,
+    zk: bool,
+) -> ProofParameters {
+    utils::proof_parameters(primitives.field.name(), primitives.hash.name(), options, zk)
+}
+
+/// Evaluates `$body` with `$hasher` bound to the hasher selected by the [`Primitives`] `$primitives`, over
+/// the selected field, returning its value, or [`UnsupportedPrimitives`] if the primitives do not pass
+/// [`Primitives::check`].
+macro_rules! with_hasher {
+    ($primitives:expr, |$hasher:ident| $body:expr) => {{
+        use winter_crypto::hashers::{Blake3_256, Rp64_256, RpJive64_256, Sha3_256};
+        use winter_math::fields::{f128, f62, f64};
+        use $crate::config::{FieldChoice, HashChoice, Primitives, UnsupportedPrimitives};
+        let primitives: Primitives = $primitives;
+        match (primitives.field, primitives.hash) {
+            (FieldChoice::F62, HashChoice::Blake3) => {
+                type $hasher = Blake3_256<f62::BaseElement>;
+                Ok($body)
+            }
+            (FieldChoice::F62, HashChoice::Sha3) => {
+                type $hasher = Sha3_256<f62::BaseElement>;
+                Ok($body)
+            }
+            (FieldChoice::F64, HashChoice::Blake3) => {
+                type $hasher = Blake3_256<f64::BaseElement>;
+                Ok($body)
+            }
+            (FieldChoice::F64, HashChoice::Sha3) => {
+                type $hasher = Sha3_256<f64::BaseElement>;
+                Ok($body)
+            }
+            (FieldChoice::F64, HashChoice::Rp64) => {
+                type $hasher = Rp64_256;
+                Ok($body)
+            }
+            (FieldChoice::F64, HashChoice::RpJive64) => {
+                type $hasher = RpJive64_256;
+                Ok($body)
+            }
+            (FieldChoice::F128, HashChoice::Blake3) => {
+                type $hasher = Blake3_256<f128::BaseElement>;
+                Ok($body)
+            }
+            (FieldChoice::F128, HashChoice::Sha3) => {
+                type $hasher = Sha3_256<f128::BaseElement>;
+                Ok($body)
+            }
+            _ => Err(UnsupportedPrimitives(primitives)),
+        }
+    }};
+}
+
+pub(crate) use with_hasher;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn primitives_round_trip() {
+        for preset in SecurityPreset::ALL {
+            for primitives in Primitives::all() {
+                let options = preset.proof_options(primitives.field);
+                for zk in [false, true] {
+                    let parameters = proof_parameters(primitives, &options, zk);
+                    assert_eq!(Primitives::from_parameters(&parameters), Some(primitives));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn algebraic_hashes_are_only_supported_over_f64() {
+        for field in FieldChoice::ALL {
+            for hash in [HashChoice::Rp64, HashChoice::RpJive64] {
+                let primitives = Primitives::new(field, hash);
+                assert_eq!(primitives.check().is_ok(), field == FieldChoice::F64);
+                // Their names are not accepted over other fields either
+                let parameters = utils::proof_parameters(
+                    field.name(),
+                    hash.name(),
+                    &default_proof_options(),
+                    false,
+                );
+                assert_eq!(
+                    Primitives::from_parameters(&parameters).is_some(),
+                    field == FieldChoice::F64
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn from_parameters_rejects_names_within_other_text() {
+        let parameters = proof_parameters(Primitives::default(), &default_proof_options(), false);
+        let hash = HashChoice::default().name();
+        for (field, hash) in [
+            (format!("f1284{}", &parameters.field[4..]), hash.to_string()),
+            (
+                format!("f62 f128{}", &parameters.field[4..]),
+                hash.to_string(),
+            ),
+            (parameters.field.clone(), format!("{hash}, Sha3_256")),
+            (parameters.field.clone(), format!("Rp64_256{hash}")),
+        ] {
+            let parameters = ProofParameters {
+                field,
+                hash,
+                ..parameters.clone()
+            };
+            assert_eq!(
+                Primitives::from_parameters(&parameters),
+                None,
+                "{parameters}"
+            );
+        }
+    }
+
+    #[test]
+    fn presets_provide_their_security() {
+        for preset in SecurityPreset::ALL {
+            for primitives in Primitives::all() {
+                let options = preset.proof_options(primitives.field);
+                for trace_length in [min_trace_length(&options), 1 << 20] {
+                    let security = security_report(primitives, &options, trace_length);
+                    assert!(
+                        security.conjectured >= preset.min_conjectured_security(),
+                        "{preset:?}, {primitives:?}: {security:?}"
+                    );
+                    if preset == SecurityPreset::Proven128 {
+                        assert!(security.proven >= 128, "{primitives:?}: {security:?}");
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! Row-level diagnostics for traces which do not satisfy the constraints.
+//!
+//! Winterfell's prover only reports such traces as an opaque `ProverError`, or produces a proof which does
+//! not verify. [`check_trace`] instead runs [`CollatzAir::evaluate_transition`] and
+//! [`CollatzAir::get_assertions`] over a `TraceTable`, and names each failing constraint along with the
+//! decoded rows it was evaluated on.
+
+use std::fmt;
+
+use collatz_core::{CollatzError, DecodedRow, TraceLayout};
+use winterfell::math::{ExtensibleField, FieldElement, StarkField};
+use winterfell::{Air, EvaluationFrame, ProofOptions, Trace, TraceTable};
+
+use crate::air::CollatzAir;
+use crate::config::NUM_MASK_COLUMNS;
+use crate::utils::PublicInputs;
+
+/// The constraints of [`CollatzAir`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Constraint {
+    /// Bit `i` of the next row is binary.
+    BitConsistency(usize),
+    TransitionFlagConsistency,
+    /// The carry bit in this column of the next row is binary, and zero unless the next row is a transition.
+    CarryBitConsistency(usize),
+    /// The Collatz rule, or the repetition of the row, on limb `k`.
+    CollatzRule(usize),
+    StepCounter,
+    DoneFlagBinary,
+    /// The done flag is only set on 1.
+    DoneFlagOnlyOnOne,
+    /// The done flag is set on every 1.
+    DoneFlagOnEveryOne,
+    DoneInverseZero,
+    /// No transition follows a row which is done.
+    NoTransitionAfterDone,
+    /// Mask column `j` of a zero-knowledge trace is zero.
+    Mask(usize),
+    /// The cell in `column` holds `expected`, e.g. a boundary bit of the first or last row.
+    Assertion {
+        column: usize,
+        expected: u128,
+    },
+}
+
+impl Constraint {
+    /// The transition constraints of `layout`, in the order `evaluate_transition` writes them.
+    pub fn transitions(layout: &TraceLayout) -> Vec<Constraint> {
+        let mut constraints: Vec<_> = (0..layout.num_bits())
+            .map(Constraint::BitConsistency)
+            .collect();
+        constraints.push(Constraint::TransitionFlagConsistency);
+        constraints.extend(layout.carry_columns().map(Constraint::CarryBitConsistency));
+        constraints.extend((0..layout.num_limbs()).map(Constraint::CollatzRule));
+        constraints.extend([
+            Constraint::StepCounter,
+            Constraint::DoneFlagBinary,
+            Constraint::DoneFlagOnlyOnOne,
+            Constraint::DoneFlagOnEveryOne,
+            Constraint::DoneInverseZero,
+            Constraint::NoTransitionAfterDone,
+        ]);
+        constraints
+    }
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constraint::BitConsistency(i) => write!(f, "consistency of bit {i}"),
+            Constraint::TransitionFlagConsistency => {
+                write!(f, "consistency of the transition flag")
+            }
+            Constraint::CarryBitConsistency(column) => {
+                write!(f, "consistency of the carry bit in column {column}")
+            }
+            Constraint::CollatzRule(k) => write!(f, "Collatz rule on limb {k}"),
+            Constraint::StepCounter => write!(f, "step counter"),
+            Constraint::DoneFlagBinary => write!(f, "consistency of the done flag"),
+            Constraint::DoneFlagOnlyOnOne => write!(f, "done flag only on 1"),
+            Constraint::DoneFlagOnEveryOne => write!(f, "done flag on every 1"),
+            Constraint::DoneInverseZero => write!(f, "zero done inverse once done"),
+            Constraint::NoTransitionAfterDone => write!(f, "no transition once done"),
+            Constraint::Mask(j) => write!(f, "zero mask column {j}"),
+            Constraint::Assertion { column, expected } => {
+                write!(f, "assertion that column {column} is {expected}")
+            }
+        }
+    }
+}
+
+/// A constraint which does not hold at `row`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConstraintFailure {
+    pub row: usize,
+    /// Index of the constraint in the result of `evaluate_transition`, or in `get_assertions`.
+    pub index: usize,
+    pub constraint: Constraint,
+    pub current: DecodedRow,
+    /// The following row, for transition constraints.
+    pub next: Option<DecodedRow>,
+}
+
+impl fmt::Display for ConstraintFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (#{}) fails at row {} (current: {}",
+            self.constraint, self.index, self.row, self.current
+        )?;
+        if let Some(next) = &self.next {
+            write!(f, "; next: {next}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Evaluates the transition constraints and the assertions of the AIR for `public_inputs` on every row of
+/// `trace`, returning every failure in row order. An empty result means that the trace is valid, and an
+/// error that the AIR cannot be instantiated for the trace's shape.
+pub fn check_trace<B>(
+    trace: &TraceTable<B>,
+    public_inputs: PublicInputs,
+    options: ProofOptions,
+) -> Result<Vec<ConstraintFailure>, CollatzError>
+where
+    B: StarkField + ExtensibleField<2> + ExtensibleField<3>,
+    B::PositiveInteger: Into<u128>,
+{
+    let layout = CollatzAir::<B>::check(trace.info(), &public_inputs, &options)?;
+    let zk = public_inputs.zk;
+    let air = CollatzAir::<B>::new(trace.info().clone(), public_inputs, options);
+    let length = trace.length();
+    let rows: Vec<Vec<B>> = (0..length)
+        .map(|row| {
+            let mut cells = vec![B::ZERO; trace.width()];
+            trace.read_row_into(row, &mut cells);
+            cells
+        })
+        .collect();
+    let decode = |row: usize| layout.decode_row(|column| rows[row][column].as_int().into());
+
+    let mut failures = Vec::new();
+    let mut transitions = Constraint::transitions(&layout);
+    if zk {
+        transitions.extend((0..NUM_MASK_COLUMNS).map(Constraint::Mask));
+    }
+    let assertions = air.get_assertions();
+    for row in 0..length {
+        // Transition constraints apply to every row but the last one, and the random rows of zero-knowledge
+        // traces along with the row before them
+        if row + air.random_rows() + 1 < length {
+            let frame = EvaluationFrame::from_rows(rows[row].clone(), rows[row + 1].clone());
+            let mut result = vec![B::ZERO; transitions.len()];
+            air.evaluate_transition(&frame, &air.periodic_values(row), &mut result);
+            for (index, _) in result
+                .iter()
+                .enumerate()
+                .filter(|(_, &value)| value != B::ZERO)
+            {
+                failures.push(ConstraintFailure {
+                    row,
+                    index,
+                    constraint: transitions[index],
+                    current: decode(row),
+                    next: Some(decode(row + 1)),
+                });
+            }
+        }
+
+        for (index, assertion) in assertions.iter().enumerate() {
+            assertion.apply(length, |step, expected| {
+                if step == row && rows[row][assertion.column()] != expected {
+                    failures.push(ConstraintFailure {
+                        row,
+                        index,
+                        constraint: Constraint::Assertion {
+                            column: assertion.column(),
+                            expected: expected.as_int().into(),
+                        },
+                        current: decode(row),
+                        next: None,
+                    });
+                }
+            });
+        }
+    }
+    Ok(failures)
+}
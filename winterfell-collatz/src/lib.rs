@@ -0,0 +1,25 @@
+//! Collatz AIR, prover and verifier built on Winterfell.
+//!
+//! The prover, and the trace generation it relies on, are behind the default `prover` feature:
+//! verifiers can depend on this crate with `default-features = false` to only get the AIR and [`verify`].
+
+pub mod air;
+#[cfg(feature = "prover")]
+pub mod backend;
+pub mod config;
+#[cfg(feature = "prover")]
+pub mod debug;
+#[cfg(feature = "prover")]
+pub mod prover;
+mod utils;
+pub mod verifier;
+
+pub use air::CollatzAir;
+#[cfg(feature = "prover")]
+pub use backend::WinterfellBackend;
+#[cfg(feature = "prover")]
+pub use debug::{check_trace, ConstraintFailure};
+#[cfg(feature = "prover")]
+pub use prover::{prove, prove_witness, CollatzProver};
+pub use utils::PublicInputs;
+pub use verifier::{proof_options, trace_length, verify, VerifyError};
@@ -1,28 +1,16 @@
-mod air;
-mod prover;
-mod utils;
-
-use air::*;
-use prover::*;
+use std::error::Error;
+use std::process::ExitCode;
 
+use collatz_core::{Backend, BigUint, ProofContainer, SequenceLimits};
 use tracing::level_filters::LevelFilter;
 use tracing_forest::ForestLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Registry};
-use utils::compute_collatz_sequence;
-use winterfell::{
-    crypto::{hashers::Blake3_256, DefaultRandomCoin, MerkleTree},
-    math::fields::f128::BaseElement,
-    verify, BatchingMethod, FieldExtension, ProofOptions, Prover,
+use winterfell_collatz::config::{
+    min_trace_length, proof_parameters, security_report, Primitives, SecurityPreset,
 };
+use winterfell_collatz::{prove, verify};
 
-// Type definitions
-type Hasher = Blake3_256<BaseElement>;
-type Merkle = MerkleTree<Hasher>;
-type Coin = DefaultRandomCoin<Hasher>;
-
-const N: usize = 6;
-
-fn main() {
+fn main() -> ExitCode {
     let env_filter = EnvFilter::builder()
         .with_default_directive(LevelFilter::INFO.into())
         .from_env_lossy();
@@ -32,39 +20,54 @@ fn main() {
         .with(ForestLayer::default())
         .init();
 
-    let starting_value = 52;
-    let sequence = compute_collatz_sequence(starting_value);
-    let max_element = sequence.iter().max().unwrap_or(&0);
-    let max_bits_in_sequence = 32 - max_element.leading_zeros() as usize;
-
-    assert_eq!(max_bits_in_sequence, N, "The number of trace columns must match the number of bits in the max element of the sequence");
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
 
-    let proof_options = ProofOptions::new(
-        28,
-        8,
-        0,
-        FieldExtension::Quadratic,
-        4,
-        7,
-        BatchingMethod::Linear,
-        BatchingMethod::Linear,
-    );
+fn run() -> Result<(), Box<dyn Error>> {
+    let starting_value: BigUint = match std::env::args().nth(1) {
+        Some(arg) => arg
+            .parse()
+            .map_err(|_| format!("invalid starting value `{arg}`, expected a positive integer"))?,
+        None => BigUint::from(52u32),
+    };
 
-    let prover = CollatzProver::<Hasher, N>::new(
-        proof_options.clone(),
+    let preset = SecurityPreset::default();
+    let primitives = Primitives::default();
+    let proof_options = preset.proof_options(primitives.field);
+    let container = prove(
         starting_value,
-        (sequence.len() - 1) as u32,
+        &SequenceLimits::default(),
+        proof_options.clone(),
+        primitives,
+        false,
+    )?;
+    let trace_length = container
+        .claim
+        .trace_length(min_trace_length(&proof_options));
+    let security = security_report(primitives, &proof_options, trace_length);
+    println!(
+        "{preset:?} options: {} bits of conjectured security, {} bits proven",
+        security.conjectured, security.proven
     );
 
-    let trace = prover.build_trace();
-    let public_inputs = prover.get_pub_inputs(&trace);
-    let proof = prover.prove(trace).unwrap();
+    // Store the proof along with its claim and parameters, optionally on disk
+    let bytes = container.to_bytes()?;
+    if let Some(path) = std::env::args().nth(2) {
+        std::fs::write(&path, &bytes)?;
+    }
 
-    let acceptable_options = winterfell::AcceptableOptions::OptionSet(vec![proof_options]);
-    assert!(verify::<CollatzAir<N>, Hasher, Coin, Merkle>(
-        proof,
-        public_inputs,
-        &acceptable_options
-    )
-    .is_ok());
+    // Verify from the serialized container, as an independent verifier would
+    let container = ProofContainer::from_bytes_strict(
+        &bytes,
+        Backend::Winterfell,
+        &proof_parameters(primitives, &proof_options, false),
+    )?;
+    verify(&container, preset.min_conjectured_security())?;
+    Ok(())
 }
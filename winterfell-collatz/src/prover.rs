@@ -1,80 +1,176 @@
-use crate::utils::compute_collatz_sequence;
+use collatz_core::{
+    Backend, BigUint, CollatzClaim, CollatzError, CollatzRow, CollatzWitness, ProofContainer,
+    SequenceLimits, TraceLayout,
+};
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
 use std::marker::PhantomData;
+use winter_utils::Randomizable;
 use winterfell::crypto::{DefaultRandomCoin, ElementHasher, MerkleTree};
-use winterfell::math::fields::f128::BaseElement;
-use winterfell::math::FieldElement;
+use winterfell::math::{ExtensibleField, FieldElement, StarkField};
 use winterfell::matrix::ColMatrix;
 use winterfell::{
     AuxRandElements, CompositionPoly, CompositionPolyTrace, ConstraintCompositionCoefficients,
     DefaultConstraintCommitment, DefaultConstraintEvaluator, DefaultTraceLde, PartitionOptions,
-    ProofOptions, Prover, StarkDomain, TraceInfo, TracePolyTable, TraceTable,
+    ProofOptions, Prover, StarkDomain, Trace, TraceInfo, TracePolyTable, TraceTable,
 };
 
 use crate::air::CollatzAir;
-use crate::utils::PublicInputs;
+use crate::config::{
+    min_trace_length, proof_parameters, with_hasher, zk_random_rows, zk_trace_length, Primitives,
+    SecurityPreset, NUM_MASK_COLUMNS,
+};
+use crate::debug::{check_trace, ConstraintFailure};
+use crate::utils::{element, PublicInputs};
 
-pub struct CollatzProver<H: ElementHasher, const N: usize> {
+/// Prover over the base field of the hasher `H`.
+pub struct CollatzProver<H: ElementHasher> {
     options: ProofOptions,
-    starting_value: u32,
+    starting_value: BigUint,
     steps_count: u32,
+    limits: SequenceLimits,
+    zk: bool,
     _hasher: PhantomData<H>,
 }
 
-impl<H: ElementHasher, const N: usize> CollatzProver<H, N> {
-    pub fn new(options: ProofOptions, starting_value: u32, steps_count: u32) -> Self {
+impl<H: ElementHasher> CollatzProver<H> {
+    /// Creates a prover for the claim that `starting_value` reaches 1 in `steps_count` steps.
+    pub fn new(options: ProofOptions, starting_value: BigUint, steps_count: u32) -> Self {
         Self {
             options,
             starting_value,
             steps_count,
+            limits: SequenceLimits::default(),
+            zk: false,
             _hasher: PhantomData,
         }
     }
 
-    pub fn build_trace(&self) -> TraceTable<BaseElement> {
-        // we need to dynamically compute the trace length, it depends on the instance starting value
-        let mut sequence = compute_collatz_sequence(self.starting_value);
-        let mut trace_length = sequence.len();
-        let num_steps = trace_length - 1;
-        // pad the trace length to the next power of 2
-        trace_length = trace_length.next_power_of_two();
-        // fill the rest of the sequence with ones
-        sequence.resize(trace_length, 1);
-
-        let mut trace = TraceTable::new(N + 2, trace_length);
-        trace.fill(
-            |state| {
-                for i in 0..N {
-                    state[i] = BaseElement::from((self.starting_value >> i) & 1);
-                }
-                state[N] = BaseElement::ZERO;
-                state[N + 1] = BaseElement::ZERO;
-            },
-            |j, state| {
-                let next_val = sequence[j + 1];
-
-                for i in 0..N {
-                    state[i] = BaseElement::from((next_val >> i) & 1);
-                }
-                if j + 1 <= num_steps {
-                    state[N] = BaseElement::from((j + 1) as u32);
-                    state[N + 1] = BaseElement::ONE;
-                } else {
-                    state[N] = BaseElement::from(num_steps as u32);
-                    state[N + 1] = BaseElement::ZERO;
-                }
-            },
-        );
-        trace
+    /// Creates a prover for the claim that `starting_value` reaches 1 in `steps_count` steps, with the proof
+    /// options of `preset`.
+    pub fn from_preset(preset: SecurityPreset, starting_value: BigUint, steps_count: u32) -> Self {
+        Self::new(
+            preset.options(H::BaseField::MODULUS_BITS),
+            starting_value,
+            steps_count,
+        )
+    }
+
+    /// Bounds the sequence the trace is built from, e.g. to a fixed bit width.
+    pub fn with_limits(mut self, limits: SequenceLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Proves zero-knowledge traces, which end with random rows and have mask columns (see
+    /// [`CollatzAir`]), so that two proofs of the same claim differ and reveal nothing but the claim.
+    pub fn with_zk(mut self, zk: bool) -> Self {
+        self.zk = zk;
+        self
+    }
+
+    fn public_inputs(&self) -> PublicInputs {
+        PublicInputs::from((self.starting_value.clone(), self.steps_count)).with_zk(self.zk)
+    }
+
+    /// Checks `trace` against the constraints for this prover's claim, e.g. before proving it, and returns
+    /// every failure (see [`check_trace`]).
+    pub fn check_trace(
+        &self,
+        trace: &TraceTable<H::BaseField>,
+    ) -> Result<Vec<ConstraintFailure>, CollatzError>
+    where
+        H::BaseField: ExtensibleField<2> + ExtensibleField<3>,
+        <H::BaseField as StarkField>::PositiveInteger: Into<u128>,
+    {
+        check_trace(trace, self.public_inputs(), self.options.clone())
+    }
+
+    /// Builds the trace of the starting value's sequence, sized to its largest element and padded to the
+    /// minimum trace length of the proof options, or the zero-knowledge trace of the sequence.
+    pub fn build_trace(&self) -> Result<TraceTable<H::BaseField>, CollatzError> {
+        // the trace length and width depend on the instance starting value
+        let witness = CollatzWitness::new(self.starting_value.clone(), &self.limits)?;
+        Ok(if self.zk {
+            zk_witness_trace(&witness, &self.options)
+        } else {
+            witness_trace(&witness.with_min_trace_length(min_trace_length(&self.options)))
+        })
     }
 }
 
-impl<H: ElementHasher, const N: usize> Prover for CollatzProver<H, N>
+/// Lays out the rows of `witness` into a trace, sized to its largest element.
+fn witness_trace<B: StarkField>(witness: &CollatzWitness) -> TraceTable<B> {
+    let layout = TraceLayout::for_witness(witness);
+    let rows: Vec<CollatzRow> = witness.rows().collect();
+
+    let mut trace = TraceTable::new(layout.width(), witness.trace_length());
+    trace.fill(
+        |state| layout.write_row(&rows[0], state, element, |x| x.inv()),
+        |j, state| layout.write_row(&rows[j + 1], state, element, |x| x.inv()),
+    );
+    trace
+}
+
+/// Lays out the rows of `witness` into the zero-knowledge trace for `options` (see [`CollatzAir`]): the
+/// sequence, padded up to the random rows, then the random rows, with the mask columns after the layout's.
+fn zk_witness_trace<B: StarkField>(
+    witness: &CollatzWitness,
+    options: &ProofOptions,
+) -> TraceTable<B> {
+    let layout = TraceLayout::for_witness(witness);
+    let width = layout.width();
+    let trace_length = zk_trace_length(options, witness.sequence().len());
+    let sequence_length = trace_length - zk_random_rows(options, trace_length);
+    // The padded witness has a power of two of rows, i.e. the trace length: it is cut at the random rows
+    let witness = witness.clone().with_min_trace_length(sequence_length);
+    let rows: Vec<CollatzRow> = witness.rows().take(sequence_length).collect();
+    let mut rng = StdRng::from_os_rng();
+
+    let mut trace = TraceTable::new(width + NUM_MASK_COLUMNS, trace_length);
+    trace.fill(
+        |state| {
+            layout.write_row(&rows[0], &mut state[..width], element, |x| x.inv());
+            state[width..].fill(B::ZERO);
+        },
+        |j, state| {
+            let row = j + 1;
+            if row < sequence_length {
+                layout.write_row(&rows[row], &mut state[..width], element, |x| x.inv());
+            } else {
+                state[..width].fill_with(|| random_element(&mut rng));
+            }
+            // The mask columns are random from the last row of the sequence, where the constraints stop
+            // applying
+            if row + 1 < sequence_length {
+                state[width..].fill(B::ZERO);
+            } else {
+                state[width..].fill_with(|| random_element(&mut rng));
+            }
+        },
+    );
+    trace
+}
+
+/// A uniformly random element of `B`.
+fn random_element<B: StarkField>(rng: &mut impl RngCore) -> B {
+    let mut bytes = vec![0; B::VALUE_SIZE];
+    loop {
+        rng.fill_bytes(&mut bytes);
+        if let Some(element) = B::from_random_bytes(&bytes) {
+            return element;
+        }
+    }
+}
+
+impl<H> Prover for CollatzProver<H>
 where
-    H: ElementHasher<BaseField = BaseElement> + Sync,
+    H: ElementHasher + Sync,
+    H::BaseField: ExtensibleField<2> + ExtensibleField<3>,
 {
-    type BaseField = BaseElement;
-    type Air = CollatzAir<N>;
-    type Trace = TraceTable<BaseElement>;
+    type BaseField = H::BaseField;
+    type Air = CollatzAir<H::BaseField>;
+    type Trace = TraceTable<H::BaseField>;
     type HashFn = H;
     type VC = MerkleTree<H>;
     type RandomCoin = DefaultRandomCoin<Self::HashFn>;
@@ -89,7 +185,7 @@ where
         &self,
         _trace: &Self::Trace,
     ) -> <<Self as Prover>::Air as winterfell::Air>::PublicInputs {
-        PublicInputs::from((self.starting_value, self.steps_count))
+        self.public_inputs()
     }
 
     fn options(&self) -> &ProofOptions {
@@ -139,3 +235,65 @@ where
         )
     }
 }
+
+/// Proves that `starting_value` reaches 1 over the field and with the hash of `primitives`, with zero
+/// knowledge if `zk`, returning the proof in a container along with its claim.
+pub fn prove(
+    starting_value: BigUint,
+    limits: &SequenceLimits,
+    options: ProofOptions,
+    primitives: Primitives,
+    zk: bool,
+) -> Result<ProofContainer, CollatzError> {
+    prove_witness(
+        CollatzWitness::new(starting_value, limits)?,
+        options,
+        primitives,
+        zk,
+    )
+}
+
+/// Proves the sequence of `witness`, padded to the minimum trace length of `options`, over the field and with
+/// the hash of `primitives`, with zero knowledge if `zk` (see [`CollatzProver::with_zk`]).
+pub fn prove_witness(
+    witness: CollatzWitness,
+    options: ProofOptions,
+    primitives: Primitives,
+    zk: bool,
+) -> Result<ProofContainer, CollatzError> {
+    let witness = witness.with_min_trace_length(min_trace_length(&options));
+    let claim = CollatzClaim::new(witness.starting_value().clone(), witness.steps());
+
+    // The number of bit columns is chosen at runtime from the largest element of the sequence
+    let (layout, proof) = with_hasher!(primitives, |H| {
+        let prover = CollatzProver::<H>::new(
+            options.clone(),
+            claim.starting_value.clone(),
+            claim.steps_count,
+        )
+        .with_zk(zk);
+        let trace = if zk {
+            zk_witness_trace(&witness, &options)
+        } else {
+            witness_trace(&witness)
+        };
+        let layout = CollatzAir::<<H as ElementHasher>::BaseField>::check(
+            trace.info(),
+            &prover.public_inputs(),
+            &options,
+        )?;
+        let proof = prover
+            .prove(trace)
+            .map_err(|error| CollatzError::Prover(Box::new(error)))?;
+        (layout, proof.to_bytes())
+    })
+    .map_err(|error| CollatzError::Prover(Box::new(error)))?;
+
+    Ok(ProofContainer {
+        backend: Backend::Winterfell,
+        parameters: proof_parameters(primitives, &options, zk),
+        claim,
+        num_bits: layout.num_bits() as u32,
+        proof,
+    })
+}
@@ -1,50 +1,108 @@
-use winterfell::math::{fields::f128::BaseElement, FieldElement, ToElements};
-
-pub(crate) fn compute_collatz_sequence(n: u32) -> Vec<u32> {
-    let mut sequence = Vec::new();
-    let mut current = n;
-
-    while current != 1 {
-        sequence.push(current);
-        if current % 2 == 0 {
-            current = current / 2;
-        } else {
-            current = 3 * current + 1;
-        }
-    }
-    sequence.push(1);
-    sequence
-}
+use collatz_core::{
+    BigUint, FriParameters, ProofParameters, TraceLayout, CARRY_BITS, CARRY_OFFSET,
+};
+use winter_air::ProofOptions;
+use winter_math::{FieldElement, StarkField, ToElements};
 
 // The PublicInputs type bound on the Air trait is required to implement the `ToElements` trait.
-// Due to the orphan rule, we need to create a newtype to hold the inner array.
-pub struct PublicInputs<const N: usize> {
-    pub values: [BaseElement; N],
-    pub steps_count: BaseElement,
+// The binary decomposition of the starting value is not part of the public inputs: it depends on the
+// trace width, which the verifier only learns from the proof's `TraceInfo`.
+pub struct PublicInputs {
+    pub starting_value: BigUint,
+    pub steps_count: u32,
+    /// Whether the trace is a zero-knowledge one, with random rows and mask columns (see
+    /// [`crate::CollatzAir`]).
+    pub zk: bool,
 }
 
-impl<const N: usize> From<(u32, u32)> for PublicInputs<N> {
-    fn from(value: (u32, u32)) -> Self {
-        let mut first = [BaseElement::ZERO; N];
-        for i in 0..N {
-            first[i] = BaseElement::from((value.0 >> i) & 1);
-        }
+impl PublicInputs {
+    pub fn with_zk(mut self, zk: bool) -> Self {
+        self.zk = zk;
+        self
+    }
+}
+
+impl From<(BigUint, u32)> for PublicInputs {
+    fn from(value: (BigUint, u32)) -> Self {
         PublicInputs {
-            values: first,
-            steps_count: BaseElement::from(value.1),
+            starting_value: value.0,
+            steps_count: value.1,
+            zk: false,
         }
     }
 }
 
-impl<const N: usize> ToElements<BaseElement> for PublicInputs<N> {
-    fn to_elements(&self) -> Vec<BaseElement> {
-        let mut elements = self.values.to_vec();
-        elements.push(self.steps_count);
+impl<B: StarkField> ToElements<B> for PublicInputs {
+    fn to_elements(&self) -> Vec<B> {
+        // Split the starting value into 32-bit digits so that it is not reduced modulo any of the fields
+        let mut elements = vec![B::from(self.steps_count), B::from(self.zk as u32)];
+        elements.extend(self.starting_value.to_u32_digits().into_iter().map(B::from));
         elements
     }
 }
 
+/// Converts `value` to an element of `B`. Not every field converts from `u64`, so this goes through its
+/// 32-bit halves.
+pub fn element<B: StarkField>(value: u64) -> B {
+    let two_pow_32 = B::from(1u32 << 16) * B::from(1u32 << 16);
+    B::from((value >> 32) as u32) * two_pow_32 + B::from(value as u32)
+}
+
+/// Parameters recorded in the proof container for proofs over the field named `field_name`, hashed with
+/// `hash_name`, with zero knowledge if `zk`.
+pub fn proof_parameters(
+    field_name: &str,
+    hash_name: &str,
+    options: &ProofOptions,
+    zk: bool,
+) -> ProofParameters {
+    let fri_options = options.to_fri_options();
+    ProofParameters {
+        field: format!("{field_name}, {:?} extension", options.field_extension()),
+        hash: hash_name.to_string(),
+        pcs: format!(
+            "FRI(folding: {}){}",
+            fri_options.folding_factor(),
+            if zk { ", masked trace" } else { "" }
+        ),
+        fri: FriParameters {
+            log_blowup: options.blowup_factor().ilog2(),
+            num_queries: options.num_queries() as u32,
+            proof_of_work_bits: options.grinding_factor(),
+            // The remainder polynomial has at most `remainder_max_degree + 1` coefficients, a power of two
+            log_final_poly_len: (fri_options.remainder_max_degree() + 1).ilog2(),
+        },
+    }
+}
+
 /// Returns zero only when a = zero || a == one.
 pub fn is_binary<E: FieldElement>(a: E) -> E {
     a * a - a
 }
+
+/// Weighted sum of the bits of limb `k` in `row`.
+pub fn limb<E: FieldElement>(layout: &TraceLayout, row: &[E], k: usize) -> E {
+    layout
+        .limb(k)
+        .rev()
+        .fold(E::ZERO, |acc, i| acc.double() + row[layout.bit(i)])
+}
+
+/// Carry into limb `k` encoded in `row`.
+/// There is no carry into the first limb, nor out of the last one.
+pub fn carry<E: FieldElement>(layout: &TraceLayout, row: &[E], k: usize) -> E {
+    if k == 0 || k == layout.num_limbs() {
+        return E::ZERO;
+    }
+    (0..CARRY_BITS)
+        .rev()
+        .fold(E::ZERO, |acc, j| acc.double() + row[layout.carry_bit(k, j)])
+        - E::from(CARRY_OFFSET as u32)
+}
+
+/// `(limb_0 - 1) + 2 * (limb_1 + ... + limb_{L-1})` in `row`, which is zero iff the row's value is 1.
+pub fn done_sum<E: FieldElement>(layout: &TraceLayout, row: &[E]) -> E {
+    (1..layout.num_limbs()).fold(limb(layout, row, 0) - E::ONE, |acc, k| {
+        acc + limb(layout, row, k).double()
+    })
+}
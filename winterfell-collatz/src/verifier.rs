@@ -0,0 +1,119 @@
+use std::fmt;
+
+use collatz_core::{Backend, CollatzError, ProofContainer, ProofFormatError};
+use winter_air::proof::Proof;
+use winter_air::ProofOptions;
+use winter_crypto::{DefaultRandomCoin, ElementHasher, MerkleTree};
+use winter_math::ExtensibleField;
+use winter_utils::DeserializationError;
+use winter_verifier::{verify as verify_proof, AcceptableOptions, VerifierError};
+
+use crate::air::CollatzAir;
+use crate::config::{proof_parameters, with_hasher, Primitives};
+use crate::utils::PublicInputs;
+
+/// Reasons why a proof container is rejected.
+#[derive(Debug)]
+pub enum VerifyError {
+    /// The container was not produced by this backend with the expected parameters, or is inconsistent.
+    Format(ProofFormatError),
+    /// The proof bytes are not a valid Winterfell proof.
+    Deserialization(DeserializationError),
+    /// The proof does not prove the container's claim.
+    Verification(VerifierError),
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::Format(error) => write!(f, "{error}"),
+            VerifyError::Deserialization(error) => write!(f, "invalid proof: {error}"),
+            VerifyError::Verification(error) => write!(f, "verification failed: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+impl From<VerifyError> for CollatzError {
+    fn from(error: VerifyError) -> Self {
+        CollatzError::Verification(Box::new(error))
+    }
+}
+
+/// Verifies the proof in `container` against its claim, accepting proofs produced with any primitives and
+/// options which provide at least `min_conjectured_security` bits of conjectured security (see
+/// [`crate::config::security_report`]).
+pub fn verify(
+    container: &ProofContainer,
+    min_conjectured_security: u32,
+) -> Result<(), VerifyError> {
+    // The proof is over the field, and hashed with the hash, named in the container
+    let primitives = Primitives::from_parameters(&container.parameters).ok_or(
+        VerifyError::Format(ProofFormatError::Malformed("parameters")),
+    )?;
+    with_hasher!(primitives, |H| verify_with::<H>(
+        container,
+        primitives,
+        min_conjectured_security
+    ))
+    .map_err(|_| VerifyError::Format(ProofFormatError::Malformed("parameters")))?
+}
+
+/// The options the proof in `container` was produced with, e.g. to find the length of its trace with
+/// [`crate::config::min_trace_length`].
+pub fn proof_options(container: &ProofContainer) -> Result<ProofOptions, VerifyError> {
+    let proof = Proof::from_bytes(&container.proof).map_err(VerifyError::Deserialization)?;
+    Ok(proof.options().clone())
+}
+
+/// Number of rows of the trace the proof in `container` was produced from, including the random rows of
+/// zero-knowledge proofs.
+pub fn trace_length(container: &ProofContainer) -> Result<usize, VerifyError> {
+    let proof = Proof::from_bytes(&container.proof).map_err(VerifyError::Deserialization)?;
+    Ok(proof.context.trace_info().length())
+}
+
+fn verify_with<H: ElementHasher>(
+    container: &ProofContainer,
+    primitives: Primitives,
+    min_conjectured_security: u32,
+) -> Result<(), VerifyError>
+where
+    H::BaseField: ExtensibleField<2> + ExtensibleField<3>,
+{
+    let proof = Proof::from_bytes(&container.proof).map_err(VerifyError::Deserialization)?;
+    // The container must record the options the proof was actually produced with, and whether it is zero
+    // knowledge, which the public inputs bind the proof to
+    let zk = container.parameters == proof_parameters(primitives, proof.options(), true);
+    container
+        .check(
+            Backend::Winterfell,
+            &proof_parameters(primitives, proof.options(), zk),
+        )
+        .map_err(VerifyError::Format)?;
+
+    let public_inputs = PublicInputs::from((
+        container.claim.starting_value.clone(),
+        container.claim.steps_count,
+    ))
+    .with_zk(zk);
+
+    // The AIR learns the layout from the proof itself, which must agree with the container and be usable,
+    // as `CollatzAir::new` panics otherwise
+    let layout = CollatzAir::<H::BaseField>::check(
+        proof.context.trace_info(),
+        &public_inputs,
+        proof.options(),
+    )
+    .map_err(|_| VerifyError::Format(ProofFormatError::Malformed("num_bits")))?;
+    if layout.num_bits() != container.num_bits as usize {
+        return Err(VerifyError::Format(ProofFormatError::Malformed("num_bits")));
+    }
+    verify_proof::<CollatzAir<H::BaseField>, H, DefaultRandomCoin<H>, MerkleTree<H>>(
+        proof,
+        public_inputs,
+        &AcceptableOptions::MinConjecturedSecurity(min_conjectured_security),
+    )
+    .map_err(VerifyError::Verification)
+}